@@ -0,0 +1,65 @@
+//! Compares checking a large document with one traversal per rule to the
+//! single traversal of `Linter`. Run with `cargo bench`.
+
+extern crate mediawiki_parser;
+extern crate mwlint;
+
+use mediawiki_parser::Traversion;
+use mwlint::*;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 10;
+
+const SECTION: &str = "== Section ==
+Some text with a [[Mathe für Nicht-Freaks: Link|link]] and <math>x^2</math>.
+
+* item one
+* item two
+** sub item
+
+{{Formel|<math>a^2 + b^2 = c^2</math>}}
+
+=== Subsection ===
+A [https://example.com link] and <dfn title=\"term\">term</dfn>.<ref>A note.</ref>
+
+{{Beweis|beweis=Text with <math>\\sum_{i=1}^n i</math>.}}
+";
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let source = SECTION.repeat(500);
+    let linter = Linter::new(Settings::default());
+    let root = mediawiki_parser::parse(&source).expect("could not parse document:");
+    let root = normalize(root, &linter.settings).expect("could not normalize document:");
+
+    // both sides run the rules of the linter, with their document hooks.
+    let context = Context::new(&root, &linter.settings);
+    let separate = time(|| {
+        for mut rule in linter.registry.rules(&linter.settings) {
+            rule.begin_document(&context)
+                .expect("error while checking rule:");
+            rule.run(&root, &linter.settings, &mut vec![])
+                .expect("error while checking rule:");
+            rule.end_document(&context)
+                .expect("error while checking rule:");
+        }
+    });
+    let single = time(|| {
+        linter.lint_element(&root);
+    });
+
+    println!("document size: {} bytes", source.len());
+    println!("one traversal per rule: {:?}", separate);
+    println!("single traversal:       {:?}", single);
+    println!(
+        "speedup: {:.2}x",
+        separate.as_secs_f64() / single.as_secs_f64()
+    );
+}
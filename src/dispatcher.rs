@@ -0,0 +1,202 @@
+use crate::rule::{Context, ElementType, Rule};
+use crate::settings::Settings;
+use mediawiki_parser::*;
+use std::any::Any;
+use std::cell::Cell;
+use std::io;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::thread;
+
+/// Marks a rule which is not called until it is reactivated explicitly.
+const STOPPED: usize = usize::max_value();
+
+/// Traverses a document once and dispatches every element to the rules
+/// interested in its type.
+///
+/// All rules share one ancestor path, which is swapped into a rule while
+/// it is called. Like with `Traversion::run`, a rule returning `false`
+/// from `work` or `work_vec` is not called for the children of the element.
+/// A rule returning an error or panicking is not called for the rest of the
+/// document.
+///
+/// Panics are caught with `catch_unwind`, which gives no isolation on wasm32:
+/// it is built with `panic = "abort"`, so a panicking rule aborts the whole
+/// module there.
+pub struct Dispatcher<'e, 's: 'e> {
+    pub rules: Vec<Box<Rule<'e, 's>>>,
+    /// Errors returned by the rules (with the index of the rule and the
+    /// span of the element it was checking).
+    pub errors: Vec<(usize, io::Error, Option<Span>)>,
+    /// Let panics of rules propagate instead of recording them as errors.
+    pub strict: bool,
+    path: Vec<&'e Element>,
+    /// For every rule, the path length at which it was stopped.
+    stopped: Vec<Option<usize>>,
+}
+
+impl<'e, 's: 'e> Dispatcher<'e, 's> {
+    pub fn new(rules: Vec<Box<Rule<'e, 's>>>) -> Self {
+        Dispatcher {
+            stopped: vec![None; rules.len()],
+            errors: vec![],
+            strict: false,
+            path: vec![],
+            rules,
+        }
+    }
+
+    /// Check a document with all rules, including their document hooks.
+    pub fn check(&mut self, context: &Context<'e, 's>, out: &mut io::Write) -> io::Result<()> {
+        for index in 0..self.rules.len() {
+            self.call(index, |rule| rule.begin_document(context).map(|_| true));
+        }
+        self.run(context.root, context.settings, out)?;
+        for index in 0..self.rules.len() {
+            if self.stopped[index].is_none() {
+                self.call(index, |rule| rule.end_document(context).map(|_| true));
+            }
+        }
+        Ok(())
+    }
+
+    // call a rule with the shared path and record its result.
+    fn call<F>(&mut self, index: usize, f: F) -> bool
+    where
+        F: FnOnce(&mut Rule<'e, 's>) -> io::Result<bool>,
+    {
+        let rule = &mut self.rules[index];
+        mem::swap(rule.path_mut(), &mut self.path);
+        let result = if self.strict {
+            f(&mut **rule)
+        } else {
+            catch_quietly(|| f(&mut **rule)).unwrap_or_else(|payload| Err(panic_error(payload)))
+        };
+        mem::swap(rule.path_mut(), &mut self.path);
+
+        match result {
+            Ok(descend) => descend,
+            Err(error) => {
+                let span = self.path.last().map(|e| e.get_position().clone());
+                self.errors.push((index, error, span));
+                self.stopped[index] = Some(STOPPED);
+                false
+            }
+        }
+    }
+}
+
+thread_local! {
+    // set while this thread calls a rule whose panics are caught.
+    static CATCHING: Cell<bool> = Cell::new(false);
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+// catch panics of a rule without printing them, as they are recorded as
+// errors. The panic hook is wrapped once and stays silent only on a thread
+// calling a rule, other panics still reach the previous hook. On wasm32 the
+// message is kept, as a panic aborts there anyway.
+fn catch_quietly<T, F: FnOnce() -> T>(f: F) -> thread::Result<T> {
+    if cfg!(not(target_arch = "wasm32")) {
+        QUIET_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if !CATCHING.with(|c| c.get()) {
+                    previous(info);
+                }
+            }));
+        });
+    }
+    CATCHING.with(|c| c.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(false));
+    result
+}
+
+// turn the payload of a panic into an error.
+fn panic_error(payload: Box<Any + Send>) -> io::Error {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown cause".to_string()
+    };
+    io::Error::new(io::ErrorKind::Other, format!("rule panicked: {}", message))
+}
+
+impl<'e, 's: 'e> Traversion<'e, &'s Settings<'s>> for Dispatcher<'e, 's> {
+    fn path_push(&mut self, e: &'e Element) {
+        self.path.push(e)
+    }
+
+    fn path_pop(&mut self) -> Option<&'e Element> {
+        // reactivate rules which did not want to see the children.
+        let depth = self.path.len();
+        for stopped in &mut self.stopped {
+            if *stopped == Some(depth) {
+                *stopped = None;
+            }
+        }
+        self.path.pop()
+    }
+
+    fn get_path(&self) -> &Vec<&'e Element> {
+        &self.path
+    }
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &'s Settings<'s>,
+        out: &mut io::Write,
+    ) -> io::Result<bool> {
+        let element_type = ElementType::from(root);
+        let depth = self.path.len();
+        for index in 0..self.rules.len() {
+            if self.stopped[index].is_some() {
+                continue;
+            }
+            let interested = self.rules[index]
+                .interests()
+                .map(|types| types.contains(&element_type))
+                .unwrap_or(true);
+            if interested
+                && !self.call(index, |rule| rule.work(root, settings, out))
+                && self.stopped[index].is_none()
+            {
+                self.stopped[index] = Some(depth);
+            }
+        }
+        Ok(self.stopped.iter().any(|s| s.is_none()))
+    }
+
+    fn run_vec(
+        &mut self,
+        content: &'e [Element],
+        settings: &'s Settings<'s>,
+        out: &mut io::Write,
+    ) -> io::Result<()> {
+        let mut skipped = vec![];
+        for index in 0..self.rules.len() {
+            if self.stopped[index].is_none()
+                && !self.call(index, |rule| rule.work_vec(content, settings, out))
+                && self.stopped[index].is_none()
+            {
+                self.stopped[index] = Some(STOPPED);
+                skipped.push(index);
+            }
+        }
+        if self.stopped.iter().any(|s| s.is_none()) {
+            for element in content {
+                self.run(element, settings, out)?;
+            }
+        }
+        for index in skipped {
+            self.stopped[index] = None;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use crate::lint::{Example, Lint, LintKind, Severity};
+use crate::linter::{internal_error, parse_sections, RuleError};
+use crate::plugin::Registry;
+use crate::project::{broken_link, missing_anchor, missing_section_target, self_link};
+use crate::rule::Checkable;
+use crate::rules::missing_file;
+use crate::settings::{RuleMeta, Settings};
+use crate::utils::escape_html;
+use mediawiki_parser::{parse, Position, Span};
+
+/// Documentation of a lint kind or code, taken from the first lint of this
+/// code found in the bad examples (see `kinds_without_examples` for the
+/// others).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KindDoc {
+    pub kind: LintKind,
+    /// The lint code, see `Lint::code`.
+    pub code: String,
+    /// Default severity, if a bad example produced the lint.
+    pub severity: Option<Severity>,
+    pub explanation: String,
+    pub explanation_long: String,
+    pub solution: String,
+    pub examples: Vec<Example>,
+}
+
+/// Documentation of a rule and the lints it produces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleDoc {
+    pub meta: RuleMeta,
+    pub kinds: Vec<KindDoc>,
+}
+
+/// Generate the documentation of all rules of a registry (including
+/// optional rules) from their examples.
+///
+/// The bad examples are checked to find out the severity and explanations
+/// of each lint code, so formula lints are only documented completely
+/// with a `tex_checker`.
+pub fn rule_docs(registry: &Registry, settings: Settings) -> Vec<RuleDoc> {
+    let settings = &settings;
+
+    let mut result = vec![];
+    for (index, rule) in registry.all_rules().iter().enumerate() {
+        let mut kinds: Vec<KindDoc> = vec![];
+        for example in rule.examples() {
+            let code = example.code();
+            if let Some(doc) = kinds.iter_mut().find(|k| k.code == code) {
+                doc.examples.push(example.clone());
+                continue;
+            }
+            let mut doc = KindDoc {
+                kind: example.kind,
+                code,
+                severity: None,
+                explanation: String::new(),
+                explanation_long: String::new(),
+                solution: String::new(),
+                examples: vec![example.clone()],
+            };
+            if let Ok(tree) = parse(&example.bad) {
+                let mut rules = registry.all_rules();
+                if let Ok(lints) = tree.check(&mut *rules[index], settings) {
+                    if let Some(lint) = lints.iter().find(|l| l.code() == doc.code) {
+                        doc.severity = Some(lint.severity.clone());
+                        doc.explanation = lint.explanation.clone();
+                        doc.explanation_long = lint.explanation_long.clone();
+                        doc.solution = lint.solution.clone();
+                    }
+                }
+            }
+            kinds.push(doc);
+        }
+        result.push(RuleDoc {
+            meta: rule.meta(),
+            kinds,
+        });
+    }
+
+    for (meta, mut kinds) in kinds_without_examples() {
+        kinds.retain(|k| code_doc(&result, &k.code).is_none());
+        match result.iter_mut().find(|r| r.meta.name == meta.name) {
+            Some(rule) => rule.kinds.extend(kinds),
+            None => result.push(RuleDoc { meta, kinds }),
+        }
+    }
+    result
+}
+
+// documentation of a lint code found without an example.
+fn lint_doc(lint: &Lint) -> KindDoc {
+    KindDoc {
+        kind: lint.kind,
+        code: lint.code(),
+        severity: Some(lint.severity.clone()),
+        explanation: lint.explanation.clone(),
+        explanation_long: lint.explanation_long.clone(),
+        solution: lint.solution.clone(),
+        examples: vec![],
+    }
+}
+
+/// Lint kinds which can not be shown with examples of a single document:
+/// they need a project, a list of media files or fail parsing or checking.
+/// They are documented by lints of the checks producing them, listed with
+/// the rule finding them, or the part of the linter (`Project` or `Linter`)
+/// if no rule does.
+fn kinds_without_examples() -> Vec<(RuleMeta, Vec<KindDoc>)> {
+    let meta = |name: &str, description: &str| RuleMeta {
+        name: name.into(),
+        description: description.into(),
+    };
+    let start = Position {
+        offset: 0,
+        line: 1,
+        col: 1,
+    };
+    let span = Span {
+        start: start.clone(),
+        end: start,
+    };
+    let error = RuleError {
+        rule: "CheckLists".into(),
+        message: "rule panicked: index out of bounds".into(),
+        position: None,
+    };
+    let parse_errors = parse_sections("== Proof ==\n{{Formel|\n")
+        .map(|(_, lints)| lints)
+        .unwrap_or_default();
+
+    let mut linter_lints = vec![internal_error(&error, &span)];
+    linter_lints.extend(parse_errors.into_iter().take(1));
+    let docs = |lints: Vec<Lint>| -> Vec<KindDoc> { lints.iter().map(lint_doc).collect() };
+    vec![
+        (
+            meta("CheckFiles", ""),
+            docs(vec![missing_file(&span, "Example.png")]),
+        ),
+        (
+            meta(
+                "Project",
+                "Checks across the articles of a project (`mwlint --project`).",
+            ),
+            docs(vec![
+                missing_section_target(&span, "Example", "proof", &[]),
+                broken_link(&span, "Example", &[]),
+                missing_anchor(&span, "Example", "Proof", &[]),
+                self_link(&span),
+            ]),
+        ),
+        (
+            meta("Linter", "Problems found while reading and checking documents."),
+            docs(linter_lints),
+        ),
+    ]
+}
+
+/// Find the documentation of a lint kind.
+pub fn kind_doc(docs: &[RuleDoc], kind: LintKind) -> Option<&KindDoc> {
+    docs.iter()
+        .flat_map(|r| r.kinds.iter())
+        .find(|k| k.kind == kind)
+}
+
+/// Find the documentation of a lint code (case-insensitive).
+pub fn code_doc<'d>(docs: &'d [RuleDoc], code: &str) -> Option<&'d KindDoc> {
+    docs.iter()
+        .flat_map(|r| r.kinds.iter())
+        .find(|k| k.code.to_lowercase() == code.trim().to_lowercase())
+}
+
+fn severity_name(severity: &Option<Severity>) -> String {
+    match *severity {
+        Some(ref severity) => format!("{:?}", severity).to_lowercase(),
+        None => "unknown".into(),
+    }
+}
+
+/// Render the documentation of a lint kind as markdown.
+pub fn kind_markdown(doc: &KindDoc, level: usize) -> String {
+    let heading = "#".repeat(level);
+    let mut result = format!("{} {}\n\n", heading, doc.code);
+    result.push_str(&format!("Severity: {}\n\n", severity_name(&doc.severity)));
+    if !doc.explanation_long.is_empty() {
+        result.push_str(&format!("{}\n\n", doc.explanation_long));
+    }
+    if !doc.solution.is_empty() {
+        result.push_str(&format!("Solution: {}\n\n", doc.solution));
+    }
+    for example in &doc.examples {
+        result.push_str(&format!("{}# Example: {}\n\n", heading, example.name));
+        result.push_str(&format!("{}\n\n```\n{}\n```\n\n", example.bad_explanation, example.bad));
+        result.push_str(&format!("{}\n\n```\n{}\n```\n\n", example.good_explanation, example.good));
+    }
+    result
+}
+
+/// Render the rule reference as markdown.
+pub fn markdown(docs: &[RuleDoc]) -> String {
+    let mut result = "# Rule Reference\n\n".to_string();
+    for rule in docs {
+        result.push_str(&format!("## {}\n\n{}\n\n", rule.meta.name, rule.meta.description));
+        for kind in &rule.kinds {
+            result.push_str(&kind_markdown(kind, 3));
+        }
+    }
+    result
+}
+
+/// Render the rule reference as html.
+pub fn html(docs: &[RuleDoc]) -> String {
+    let mut result = "<h1>Rule Reference</h1>\n".to_string();
+    for rule in docs {
+        result.push_str(&format!(
+            "<h2 id=\"{}\">{}</h2>\n<p>{}</p>\n",
+            rule.meta.name.to_lowercase(),
+            escape_html(&rule.meta.name),
+            escape_html(&rule.meta.description)
+        ));
+        for kind in &rule.kinds {
+            result.push_str(&format!(
+                "<h3 id=\"{}\">{}</h3>\n<p>Severity: {}</p>\n",
+                escape_html(&kind.code.to_lowercase()),
+                escape_html(&kind.code),
+                severity_name(&kind.severity)
+            ));
+            if !kind.explanation_long.is_empty() {
+                result.push_str(&format!("<p>{}</p>\n", escape_html(&kind.explanation_long)));
+            }
+            if !kind.solution.is_empty() {
+                result.push_str(&format!("<p>Solution: {}</p>\n", escape_html(&kind.solution)));
+            }
+            for example in &kind.examples {
+                result.push_str(&format!(
+                    "<h4>Example: {}</h4>\n\
+                     <p>{}</p>\n<pre class=\"bad\">{}</pre>\n\
+                     <p>{}</p>\n<pre class=\"good\">{}</pre>\n",
+                    escape_html(&example.name),
+                    escape_html(&example.bad_explanation),
+                    escape_html(&example.bad),
+                    escape_html(&example.good_explanation),
+                    escape_html(&example.good)
+                ));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_reference() {
+        let docs = rule_docs(&Registry::default(), Settings::default());
+        let doc = kind_doc(&docs, LintKind::InsecureLink).unwrap();
+        assert_eq!(doc.severity, Some(Severity::Warning));
+        assert!(!doc.explanation_long.is_empty());
+        assert!(markdown(&docs).contains("## CheckExternalLinks"));
+        assert!(html(&docs).contains("<h3 id=\"insecurelink\">InsecureLink</h3>"));
+
+        for code in &["MissingFile", "SelfLink", "ParseError", "InternalError"] {
+            let doc = code_doc(&docs, code).unwrap();
+            assert!(doc.severity.is_some());
+            assert!(!doc.explanation_long.is_empty());
+        }
+    }
+}
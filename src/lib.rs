@@ -1,7 +1,10 @@
 extern crate mediawiki_parser;
 extern crate mfnf_template_spec;
 extern crate mwparser_utils;
+extern crate regex;
 extern crate serde;
+extern crate serde_json;
+extern crate serde_yaml;
 #[macro_use]
 extern crate serde_derive;
 extern crate colored;
@@ -13,12 +16,17 @@ mod settings;
 /// Data structures for defining rules.
 #[macro_use]
 mod rule;
+/// Helper functions for working with syntax trees.
+mod utils;
+/// Declarative specification of allowed templates.
+pub mod template_spec;
 
 /// common imports for rules.
 mod preamble {
-    pub use crate::lint::{Example, Lint, LintKind, Severity};
+    pub use crate::lint::{Example, Lint, LintKind, Replacement, Severity};
     pub use crate::rule::*;
     pub use crate::settings::{RuleMeta, Settings};
+    pub use crate::utils::*;
     pub use mediawiki_parser::*;
     pub use mwparser_utils::*;
     pub use std::io;
@@ -27,17 +35,47 @@ mod preamble {
 
 /// The checking functions themselves.
 mod rules;
+/// Checks across the articles of a project.
+mod project;
+/// Usage statistics over many documents.
+mod stats;
+/// Rule reference generated from the rule examples.
+pub mod docs;
+/// Traversing a document once for all rules.
+mod dispatcher;
+/// Running all rules on a document.
+mod linter;
+/// Rules and lint codes provided by other crates.
+mod plugin;
+/// Selecting elements with CSS-like selectors.
+mod query;
+/// Printing syntax trees for inspection.
+mod outline;
+/// Normalization steps applied before checking.
+mod transformations;
 
-pub use crate::lint::{Example, Lint, Severity};
-pub use crate::rule::{Checkable, Rule};
+pub use crate::dispatcher::Dispatcher;
+pub use crate::lint::{Example, Lint, LintKind, Replacement, Severity};
+pub use crate::linter::{parse_sections, Linter, Report, RuleError, RuleTiming};
+pub use crate::plugin::{LintCode, PluginConstructor, PluginRule, Registry};
+pub use crate::outline::outline;
+pub use crate::project::{
+    check_project, read_article, read_document, read_raw_document, Article, ArticleLint, Project,
+};
+pub use crate::query::{select, Selector};
+pub use crate::rule::{Checkable, Context, DocumentHooks, ElementType, Rule};
 pub use crate::rules::*;
 pub use crate::settings::Settings;
+pub use crate::stats::Stats;
+pub use crate::transformations::Normalization;
 
-/// Applies transformations to normalize the input tree.
+/// Applies the normalization steps of the settings to the input tree.
 pub fn normalize(
     mut root: mediawiki_parser::Element,
-    _settings: &settings::Settings,
+    settings: &settings::Settings,
 ) -> mediawiki_parser::transformations::TResult {
-    root = mwparser_utils::transformations::convert_template_list(root)?;
+    for step in &settings.normalization {
+        root = transformations::apply(*step, root, settings)?;
+    }
     Ok(root)
 }
@@ -19,6 +19,33 @@ pub struct Lint {
     pub severity: Severity,
     /// The lint kind.
     pub kind: LintKind,
+    /// Source changes fixing the issue, if it can be fixed automatically.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replacements: Vec<Replacement>,
+    /// Namespaced code of a lint of a plugin rule (with kind `Custom`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl Lint {
+    /// The lint code: the name of the lint kind for built-in lints,
+    /// a namespaced code like `project::rule` for lints of plugin rules.
+    pub fn code(&self) -> String {
+        match self.code {
+            Some(ref code) => code.clone(),
+            None => format!("{:?}", self.kind),
+        }
+    }
+}
+
+/// Replaces the source text at a position by a new text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "lowercase")]
+pub struct Replacement {
+    /// Source range to replace.
+    pub position: Span,
+    /// The new text.
+    pub text: String,
 }
 
 /// Defines possible kinds of lints.
@@ -27,10 +54,12 @@ pub struct Lint {
 pub enum LintKind {
     MaxHeadingDepthViolation,
     InconsistentHeadingHierarchy,
+    DuplicateHeading,
     DefinitionTermWithoutDef,
     DefinitionWithoutTerm,
     ListOneElement,
     ListMixedType,
+    SplitList,
     TemplateNotAllowed,
     InvalidTemplateName,
     DeprecatedTemplateName,
@@ -39,6 +68,11 @@ pub enum LintKind {
     MissingTemplateArgument,
     IllegalArgument,
     IllegalArgumentContent,
+    DuplicateTemplateArgument,
+    IllegalTemplateNesting,
+    TemplateArgumentOrder,
+    TemplateLayout,
+    EmptyTemplateArgument,
     MathSyntaxError,
     MathLexingError,
     MathUnknownFunction,
@@ -46,7 +80,35 @@ pub enum LintKind {
     DeprecatedRightarrow,
     DeprecatedLeftrightarrow,
     IllegalHtml,
+    IllegalHtmlAttribute,
+    UnknownRefGroup,
+    EmptyRef,
+    MissingReferences,
+    UnusedRefName,
+    UnclosedSection,
+    MismatchedSection,
     IllegalSectionName,
+    MissingSectionTarget,
+    BrokenInternalLink,
+    MissingAnchor,
+    SelfLink,
+    FileWithoutCaption,
+    FileWithoutAlt,
+    IllegalFileOption,
+    UnknownFileNamespace,
+    UnsupportedFileType,
+    MissingFile,
+    DeniedLinkDomain,
+    InsecureLink,
+    LinkWithoutCaption,
+    InterwikiLink,
+    DuplicateExternalLink,
+    /// A part of the document which could not be parsed.
+    ParseError,
+    /// A rule failed to check the document.
+    InternalError,
+    /// Lint of a plugin rule, identified by its code.
+    Custom,
 }
 
 /// Specifies examples for linter rules.
@@ -65,6 +127,19 @@ pub struct Example {
     pub good_explanation: String,
     /// The type of lint it should emitt.
     pub kind: LintKind,
+    /// Namespaced code of the lint it should emit, for plugin rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl Example {
+    /// The code of the lint it should emit, see `Lint::code`.
+    pub fn code(&self) -> String {
+        match self.code {
+            Some(ref code) => code.clone(),
+            None => format!("{:?}", self.kind),
+        }
+    }
 }
 
 /// The issue severity.
@@ -97,12 +172,15 @@ impl fmt::Display for Lint {
             self.explanation
         );
         let fancy = match self.severity {
-            Severity::Info => format!("INFO: {} ({:?})", message, self.kind).blue(),
-            Severity::Warning => format!("WARNING: {} ({:?})", message, self.kind).bright_yellow(),
-            Severity::Error => format!("ERROR: {} ({:?})", message, self.kind).red(),
+            Severity::Info => format!("INFO: {} ({})", message, self.code()).blue(),
+            Severity::Warning => format!("WARNING: {} ({})", message, self.code()).bright_yellow(),
+            Severity::Error => format!("ERROR: {} ({})", message, self.code()).red(),
         };
         writeln!(f, "{}", fancy.bold())?;
         writeln!(f, "{} {}", "try:".green().bold(), self.solution)?;
+        if !self.replacements.is_empty() {
+            writeln!(f, "{}", "(automatic fix available)".green())?;
+        }
         writeln!(f, "{}", self.explanation_long)
     }
 }
@@ -0,0 +1,415 @@
+use crate::dispatcher::Dispatcher;
+use crate::lint::{Lint, LintKind, Severity};
+use crate::plugin::Registry;
+use crate::project::{read_article, Article};
+use crate::rule::Context;
+use crate::settings::Settings;
+use mediawiki_parser::transformations::*;
+use mediawiki_parser::*;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Runs all rules on documents.
+pub struct Linter<'p> {
+    pub settings: Settings<'p>,
+    /// The rules to run, including plugin rules.
+    pub registry: Registry,
+    /// Let rules panic instead of reporting their failures as
+    /// `InternalError` lints (for development).
+    pub strict: bool,
+    /// Measure the time each rule takes to check a document. The rules
+    /// then traverse the document one after another, which is slower.
+    /// Ignored on wasm32, which has no clock.
+    pub timings: bool,
+}
+
+/// Time a rule took to check a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleTiming {
+    pub rule: String,
+    pub time: Duration,
+}
+
+/// A rule which failed to check a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleError {
+    pub rule: String,
+    pub message: String,
+    /// Span of the element the rule was checking.
+    pub position: Option<Span>,
+}
+
+/// The result of checking a document.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Report {
+    /// Lints ordered by their position, without duplicates.
+    pub lints: Vec<Lint>,
+    /// Time of every rule, only measured with `Linter::timings`.
+    pub timings: Vec<RuleTiming>,
+    pub errors: Vec<RuleError>,
+    /// The lints of every rule which found some, by rule name. Failures of
+    /// a rule are listed as its `InternalError` lints.
+    #[serde(skip)]
+    pub rule_lints: Vec<(String, Vec<Lint>)>,
+}
+
+impl<'p> Linter<'p> {
+    pub fn new(settings: Settings<'p>) -> Self {
+        Linter {
+            settings,
+            registry: Registry::default(),
+            strict: false,
+            timings: false,
+        }
+    }
+
+    /// Check a (normalized) syntax tree.
+    pub fn lint_element(&self, root: &Element) -> Report {
+        self.lint_context(&Context::new(root, &self.settings))
+    }
+
+    /// Check an article of a project, including the lints found while
+    /// reading it.
+    pub fn lint_article(&self, article: &Article) -> Report {
+        let mut context = Context::new(&article.root, &self.settings);
+        context.title = Some(article.title.as_str());
+        let mut report = self.lint_context(&context);
+        report.lints.extend(article.lints.iter().cloned());
+        report.lints = sorted_lints(report.lints);
+        report
+    }
+
+    /// Check a document with all information known about it.
+    pub fn lint_context(&self, context: &Context) -> Report {
+        let mut report = Report::default();
+        let rules = self.registry.rules(&self.settings);
+        let groups = if self.timings && cfg!(not(target_arch = "wasm32")) {
+            rules.into_iter().map(|rule| vec![rule]).collect()
+        } else {
+            vec![rules]
+        };
+
+        let mut lints = vec![];
+        for rules in groups {
+            let mut dispatcher = Dispatcher::new(rules);
+            dispatcher.strict = self.strict;
+            let (result, time) = timed(|| dispatcher.check(context, &mut vec![]));
+            if let Err(error) = result {
+                report.errors.push(RuleError {
+                    rule: "traversal".into(),
+                    message: error.to_string(),
+                    position: None,
+                });
+            }
+
+            let names: Vec<String> = dispatcher.rules.iter().map(|r| r.meta().name).collect();
+            for (index, error, position) in &dispatcher.errors {
+                report.errors.push(RuleError {
+                    rule: names[*index].clone(),
+                    message: error.to_string(),
+                    position: position.clone(),
+                });
+            }
+            // with timings, every dispatcher runs exactly one rule.
+            if let (true, Some(time)) = (self.timings, time) {
+                report.timings.push(RuleTiming {
+                    rule: names[0].clone(),
+                    time,
+                });
+            }
+            for (name, rule) in names.into_iter().zip(&dispatcher.rules) {
+                if !rule.lints().is_empty() {
+                    lints.extend(rule.lints().iter().cloned());
+                    report.rule_lints.push((name, rule.lints().to_vec()));
+                }
+            }
+        }
+        if !self.strict {
+            for error in &report.errors {
+                let lint = internal_error(error, context.root.get_position());
+                lints.push(lint.clone());
+                report.rule_lints.push((error.rule.clone(), vec![lint]));
+            }
+        }
+
+        report.lints = sorted_lints(lints);
+        report
+    }
+
+    /// Parse, normalize and check MediaWiki markup. Markup which can not be
+    /// parsed is checked without the sections which fail (see
+    /// `parse_sections`).
+    pub fn lint_str(&self, source: &str) -> Result<Report, MWError> {
+        let (root, parse_errors) = parse_sections(source)?;
+        let root = crate::normalize(root, &self.settings).map_err(MWError::TransformationError)?;
+        let mut context = Context::new(&root, &self.settings);
+        context.source = Some(source);
+        let mut report = self.lint_context(&context);
+        report.lints.extend(parse_errors);
+        report.lints = sorted_lints(report.lints);
+        Ok(report)
+    }
+
+    /// Check a file containing a syntax tree (`.json`) or MediaWiki markup,
+    /// see `read_article`.
+    pub fn lint_file(&self, path: &Path) -> io::Result<Report> {
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let article = read_article(path, &title, &self.settings)?;
+        Ok(self.lint_article(&article))
+    }
+}
+
+/// Parse MediaWiki markup. If it can not be parsed, its sections (split at
+/// the top-level headings) are parsed one by one and combined into one
+/// document without the sections which fail. These are returned as
+/// `ParseError` lints.
+pub fn parse_sections(source: &str) -> Result<(Element, Vec<Lint>), MWError> {
+    match parse(source) {
+        Err(MWError::ParseError(_)) => (),
+        result => return result.map(|root| (root, vec![])),
+    }
+
+    let mut content = vec![];
+    let mut lints = vec![];
+    for section in split_sections(source) {
+        let root = match parse(section.text) {
+            Ok(root) => root,
+            Err(MWError::ParseError(error)) => {
+                lints.push(parse_error(&section, &error));
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        let root = shift_positions(root, &section).map_err(MWError::TransformationError)?;
+        if let Element::Document(document) = root {
+            content.extend(document.content);
+        }
+    }
+    let root = Element::Document(Document {
+        position: Span {
+            start: Position {
+                offset: 0,
+                line: 1,
+                col: 1,
+            },
+            end: end_position(source),
+        },
+        content,
+    });
+    Ok((root, lints))
+}
+
+// run a function and measure how long it took, if there is a clock.
+#[cfg(not(target_arch = "wasm32"))]
+fn timed<T, F: FnOnce() -> T>(f: F) -> (T, Option<Duration>) {
+    let start = Instant::now();
+    let result = f();
+    (result, Some(start.elapsed()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn timed<T, F: FnOnce() -> T>(f: F) -> (T, Option<Duration>) {
+    (f(), None)
+}
+
+/// A part of a document starting at a top-level heading.
+struct Section<'s> {
+    text: &'s str,
+    /// Line of the document the section starts at, counted from 0.
+    line: usize,
+    /// Byte offset of the section in the document.
+    offset: usize,
+}
+
+impl<'s> Section<'s> {
+    /// Move a span of the section to its position in the document.
+    fn shift(&self, span: &mut Span) {
+        for position in &mut [&mut span.start, &mut span.end] {
+            position.line += self.line;
+            position.offset += self.offset;
+        }
+    }
+}
+
+// depth of a line like `== Heading ==`.
+fn heading_depth(line: &str) -> Option<usize> {
+    let line = line.trim_end();
+    let depth = line.chars().take_while(|c| *c == '=').count();
+    let closing = line.chars().rev().take_while(|c| *c == '=').count();
+    if depth > 0 && closing >= depth && line.len() > 2 * depth {
+        Some(depth)
+    } else {
+        None
+    }
+}
+
+/// Split a document at the headings of the lowest depth.
+fn split_sections(source: &str) -> Vec<Section> {
+    let top = source.lines().filter_map(heading_depth).min();
+    let mut sections = vec![];
+    let mut start = Section {
+        text: source,
+        line: 0,
+        offset: 0,
+    };
+    let mut offset = 0;
+    for (index, line) in source.split('\n').enumerate() {
+        if index > 0 && top.is_some() && heading_depth(line) == top {
+            start.text = &source[start.offset..offset];
+            sections.push(start);
+            start = Section {
+                text: source,
+                line: index,
+                offset,
+            };
+        }
+        offset += line.len() + 1;
+    }
+    start.text = &source[start.offset..];
+    sections.push(start);
+    sections
+}
+
+// move the positions of a section tree to the document.
+fn shift_positions(mut root: Element, section: &Section) -> TResult {
+    section.shift(root.get_position_mut());
+    if let Element::HtmlTag(ref mut tag) = root {
+        for attribute in &mut tag.attributes {
+            section.shift(&mut attribute.position);
+        }
+    }
+    recurse_inplace(&shift_positions, root, section)
+}
+
+// the position after the last character of a text.
+fn end_position(text: &str) -> Position {
+    Position {
+        offset: text.len(),
+        line: text.lines().count().max(1),
+        col: text.lines().last().map(|l| l.chars().count()).unwrap_or(0) + 1,
+    }
+}
+
+fn parse_error(section: &Section, error: &ParseError) -> Lint {
+    let mut position = Span {
+        start: error.position.clone(),
+        end: end_position(section.text),
+    };
+    section.shift(&mut position);
+    Lint {
+        position,
+        explanation: format!(
+            "This section could not be parsed, expected one of: {}.",
+            error.expected.join(", ")
+        ),
+        explanation_long: "The parser failed on this section, so it could not be \
+                           checked. Usually a template, link or html tag is not \
+                           closed properly."
+            .into(),
+        solution: "Close the markup started at this position.".into(),
+        severity: Severity::Error,
+        kind: LintKind::ParseError,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+/// Lint for a failed rule, at the element it was checking or else at the
+/// `fallback` span.
+pub(crate) fn internal_error(error: &RuleError, fallback: &Span) -> Lint {
+    Lint {
+        position: error.position.clone().unwrap_or_else(|| fallback.clone()),
+        explanation: format!("The rule `{}` failed: {}", error.rule, error.message),
+        explanation_long: "A rule failed while checking this element, so problems \
+                           found by this rule may be missing for the rest of the \
+                           document. The other rules are not affected. This is a \
+                           bug in the linter, not in the document."
+            .into(),
+        solution: "Please report this error together with the document.".into(),
+        severity: Severity::Error,
+        kind: LintKind::InternalError,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+/// Sort lints by their position and remove duplicates.
+fn sorted_lints(mut lints: Vec<Lint>) -> Vec<Lint> {
+    let position = |l: &Lint| {
+        (
+            l.position.start.line,
+            l.position.start.col,
+            l.position.end.line,
+            l.position.end.col,
+        )
+    };
+    // equal lints become neighbours, so `dedup` finds them.
+    lints.sort_by(|a, b| {
+        position(a)
+            .cmp(&position(b))
+            .then_with(|| a.code().cmp(&b.code()))
+            .then_with(|| a.explanation.cmp(&b.explanation))
+    });
+    lints.dedup();
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_recovery() {
+        let source =
+            "== A ==\n{{Formel|<math>x</math>}}\n\n== B ==\n{{Formel|\n\n== C ==\n* single item\n";
+        let linter = Linter::new(Settings::default());
+        let report = linter.lint_str(source).unwrap();
+        let errors: Vec<_> = report
+            .lints
+            .iter()
+            .filter(|l| l.kind == LintKind::ParseError)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        let line = errors[0].position.start.line;
+        assert!((4..7).contains(&line), "parse error outside of section B");
+        let list = report
+            .lints
+            .iter()
+            .find(|l| l.kind == LintKind::ListOneElement);
+        assert_eq!(list.unwrap().position.start.line, 8);
+    }
+
+    #[test]
+    fn document_checks_across_sections() {
+        let source = "== A ==\nFact.<ref>Some book</ref>\n\n== B ==\n{{Formel|\n\n\
+                      == A ==\n<references />\n";
+        let linter = Linter::new(Settings::default());
+        let report = linter.lint_str(source).unwrap();
+        let kinds: Vec<LintKind> = report.lints.iter().map(|l| l.kind).collect();
+        assert!(!kinds.contains(&LintKind::MissingReferences));
+        let duplicate = report
+            .lints
+            .iter()
+            .find(|l| l.kind == LintKind::DuplicateHeading);
+        assert_eq!(duplicate.unwrap().position.start.line, 7);
+    }
+
+    #[test]
+    fn lints_by_rule() {
+        let linter = Linter::new(Settings::default());
+        let report = linter.lint_str("* single item\n").unwrap();
+        let (rule, lints) = report
+            .rule_lints
+            .iter()
+            .find(|(_, lints)| lints.iter().any(|l| l.kind == LintKind::ListOneElement))
+            .unwrap();
+        assert_eq!(rule, "CheckLists");
+        assert!(lints.iter().all(|l| report.lints.contains(l)));
+    }
+}
@@ -1,19 +1,21 @@
 extern crate mediawiki_parser;
+#[macro_use]
 extern crate serde_json;
 extern crate serde_yaml;
 #[macro_use]
 extern crate structopt;
-extern crate mfnf_template_spec;
 extern crate mwlint;
 extern crate mwparser_utils;
 
-use mfnf_template_spec::markdown;
+use mwlint::template_spec;
 use mwlint::*;
 use mwparser_utils::CachedTexChecker;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 use std::process;
+use std::time::Duration;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -27,18 +29,254 @@ struct Args {
     /// Dump the default settings to stdout.
     #[structopt(long = "dump-config")]
     dump_config: bool,
-    /// Dump the template specification as markdown.
+    /// Dump the template documentation.
     #[structopt(long = "dump-docs")]
     dump_template_docs: bool,
-    /// Path to the input file.
+    /// Dump the reference of all rules and their lints.
+    #[structopt(long = "dump-rules")]
+    dump_rules: bool,
+    /// Format of the documentation (markdown, html or json).
+    #[structopt(
+        long = "docs-format",
+        default_value = "markdown",
+        raw(possible_values = r#"&["markdown", "html", "json"]"#)
+    )]
+    docs_format: String,
+    /// Dump the template specification as YAML (e.g. as a template for `--template-spec`).
+    #[structopt(long = "dump-template-spec")]
+    dump_template_spec: bool,
+    /// Path to the input file, a syntax tree or MediaWiki markup (if it
+    /// does not end in `.json`).
     #[structopt(parse(from_os_str), short = "i", long = "input")]
     input_file: Option<PathBuf>,
     /// Path to the config file.
     #[structopt(parse(from_os_str), short = "c", long = "config")]
     config: Option<PathBuf>,
+    /// Path to a template specification (YAML or JSON) replacing the built-in one.
+    #[structopt(parse(from_os_str), long = "template-spec")]
+    template_spec: Option<PathBuf>,
     /// Path to the texvccheck binary (formula checking).
     #[structopt(parse(from_os_str), short = "p", long = "texvccheck-path")]
     texvccheck_path: Option<PathBuf>,
+    /// Path to a list of available media files, one file name per line.
+    #[structopt(parse(from_os_str), long = "media-manifest")]
+    media_manifest: Option<PathBuf>,
+    /// Check all articles in a directory against each other.
+    #[structopt(parse(from_os_str), long = "project")]
+    project: Option<PathBuf>,
+    /// When to print examples for the lints: never, once per lint kind
+    /// (after all lints) or after every lint.
+    #[structopt(
+        long = "examples",
+        default_value = "once",
+        raw(possible_values = r#"&["never", "once", "always"]"#)
+    )]
+    examples: String,
+    /// Fail on panics and errors of rules instead of reporting them as
+    /// `InternalError` lints.
+    #[structopt(long = "strict-internal")]
+    strict_internal: bool,
+    /// Print the time each rule takes (as markdown to stderr). Slower, as
+    /// the rules then check the documents one after another.
+    #[structopt(long = "timings")]
+    timings: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Explain a kind of lint with examples.
+    #[structopt(name = "explain")]
+    Explain {
+        /// Name of the lint kind, e.g. `ListOneElement`, or the code of a
+        /// plugin rule.
+        code: String,
+    },
+    /// Print usage statistics of templates, arguments, lints and html tags
+    /// (as markdown to stderr and JSON to stdout). Reads a syntax tree from
+    /// stdin if neither files nor a project are given.
+    #[structopt(name = "stats")]
+    Stats {
+        /// Count the articles of a project directory.
+        #[structopt(parse(from_os_str), long = "project")]
+        project: Option<PathBuf>,
+        /// Files containing MediaWiki markup or syntax trees (`.json`).
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+    /// Print the spans of all elements matching a selector,
+    /// e.g. `template[name=formel] > list`.
+    #[structopt(name = "query")]
+    Query {
+        /// The selector to match.
+        selector: Selector,
+        /// Files containing MediaWiki markup or syntax trees (`.json`).
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+    /// Print the syntax tree of a file as the rules see it.
+    #[structopt(name = "ast")]
+    Ast {
+        /// Print the tree before normalization.
+        #[structopt(long = "raw", conflicts_with = "normalized")]
+        raw: bool,
+        /// Print the normalized tree (the default).
+        #[structopt(long = "normalized")]
+        normalized: bool,
+        /// Print the tree as JSON instead of indented text.
+        #[structopt(long = "json")]
+        json: bool,
+        /// Show which rule produced which lint at which element (of the
+        /// normalized tree).
+        #[structopt(long = "lints", conflicts_with = "raw")]
+        lints: bool,
+        /// File containing MediaWiki markup or a syntax tree (`.json`).
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+fn print_examples<'e, 's: 'e>(code: &str, rules: &[Box<Rule<'e, 's>>]) {
+    let examples = get_examples(rules, code);
+    if examples.is_empty() {
+        return;
+    }
+    eprintln!("Examples:");
+    for example in examples {
+        eprintln!("{}", example);
+    }
+}
+
+/// Print lints (optionally with the article they were found in), the
+/// examples of the rules of the linter and a summary to stderr.
+fn print_report(linter: &Linter, lints: &[(Option<&str>, &Lint)], examples: &str) {
+    let rules = linter.registry.rules(&linter.settings);
+    let mut codes = vec![];
+    let mut counts = BTreeMap::new();
+    for (article, lint) in lints {
+        if let Some(article) = article {
+            eprintln!("In `{}`:", article);
+        }
+        eprintln!("{}", lint);
+        let code = lint.code();
+        if examples == "always" {
+            print_examples(&code, &rules);
+        }
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+        let key = (format!("{:?}", lint.severity), lint.code());
+        *counts.entry(key).or_insert(0) += 1;
+    }
+
+    codes.retain(|code| !get_examples(&rules, code).is_empty());
+    if examples == "once" && !codes.is_empty() {
+        eprintln!("# Examples\n");
+        for code in codes {
+            eprintln!("## {}", code);
+            print_examples(&code, &rules);
+            eprintln!();
+        }
+    }
+
+    eprintln!("# Summary\n");
+    eprintln!("| severity | code | count |");
+    eprintln!("|----------|------|------:|");
+    for ((severity, code), count) in &counts {
+        eprintln!("| {} | {} | {} |", severity, code, count);
+    }
+    eprintln!("\n{} lints in total.", lints.len());
+}
+
+/// Print the lints of a single document and their JSON to stdout.
+fn print_lints(linter: &Linter, lints: &[Lint], examples: &str) {
+    let report: Vec<_> = lints.iter().map(|l| (None, l)).collect();
+    print_report(linter, &report, examples);
+
+    println!(
+        "{}",
+        &serde_json::to_string(lints).expect("could not serialize lints:")
+    );
+}
+
+/// Add the rule timings of a report to the total time of each rule.
+fn add_timings(timings: &mut BTreeMap<String, Duration>, report: &Report) {
+    for timing in &report.timings {
+        *timings
+            .entry(timing.rule.clone())
+            .or_insert_with(Duration::default) += timing.time;
+    }
+}
+
+/// With `--timings`, print the total time of each rule.
+fn print_timings(timings: &BTreeMap<String, Duration>) {
+    if timings.is_empty() {
+        return;
+    }
+    eprintln!("# Timings\n");
+    eprintln!("| rule | time |");
+    eprintln!("|------|-----:|");
+    for (rule, time) in timings {
+        eprintln!("| {} | {:?} |", rule, time);
+    }
+}
+
+/// With `--strict-internal`, print the errors of rules and fail.
+fn check_errors(report: &Report, strict: bool) {
+    if strict && !report.errors.is_empty() {
+        for error in &report.errors {
+            eprintln!(
+                "error while checking rule {}: {}",
+                error.rule, error.message
+            );
+        }
+        process::exit(1);
+    }
+}
+
+/// Lint the articles of a project, the given files or the syntax tree from
+/// stdin and count what they use.
+fn collect_stats(
+    linter: &Linter,
+    project: Option<PathBuf>,
+    files: &[PathBuf],
+) -> io::Result<Stats> {
+    let settings = &linter.settings;
+    let mut articles = match project {
+        Some(ref path) => Project::from_dir(path, settings)?.articles,
+        None => vec![],
+    };
+    for path in files {
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        articles.push(read_article(path, &title, settings)?);
+    }
+
+    let mut stats = Stats::default();
+    if project.is_none() && files.is_empty() {
+        let root = serde_json::from_reader(io::stdin()).expect("Error reading input:");
+        let root = normalize(root, settings).expect("Input normalization error:");
+        let report = linter.lint_element(&root);
+        check_errors(&report, linter.strict);
+        stats.add_document(&root, &report.lints, settings);
+    }
+    for article in &articles {
+        let report = linter.lint_article(article);
+        check_errors(&report, linter.strict);
+        stats.add_document(&article.root, &report.lints, settings);
+    }
+    Ok(stats)
+}
+
+fn print_stats(stats: &Stats) {
+    eprintln!("{}", stats.markdown());
+    println!(
+        "{}",
+        &serde_json::to_string(stats).expect("could not serialize statistics:")
+    );
 }
 
 fn main() -> Result<(), std::io::Error> {
@@ -51,6 +289,10 @@ fn main() -> Result<(), std::io::Error> {
         Settings::default()
     };
 
+    if let Some(path) = args.template_spec {
+        settings.template_spec = template_spec::load(&path)?;
+    }
+
     // dump settings
     if args.dump_config {
         println!(
@@ -61,10 +303,37 @@ fn main() -> Result<(), std::io::Error> {
     }
 
     // dump template spec
+    if args.dump_template_spec {
+        println!(
+            "{}",
+            serde_yaml::to_string(&settings.template_spec)
+                .expect("Could not serialize template specification!")
+        );
+        process::exit(0);
+    }
+
     if args.dump_template_docs {
-        println!("# Template Documentation\n");
-        for template in &settings.template_spec {
-            println!("{:?}\n", markdown::template_description(&template, 1));
+        let specs = &settings.template_spec;
+        match args.docs_format.as_str() {
+            "html" => {
+                println!("<h1>Template Documentation</h1>");
+                for template in specs {
+                    println!("{}", template_spec::html(template, 2));
+                }
+            }
+            "json" => {
+                let docs: Vec<_> = specs.iter().map(template_spec::json).collect();
+                println!(
+                    "{}",
+                    serde_json::to_string(&docs).expect("Could not serialize documentation!")
+                );
+            }
+            _ => {
+                println!("# Template Documentation\n");
+                for template in specs {
+                    println!("{}", template_spec::markdown(template, 2));
+                }
+            }
         }
         process::exit(0);
     }
@@ -75,37 +344,186 @@ fn main() -> Result<(), std::io::Error> {
         eprintln!("Warning: no texvccheck path, won't perform checks!");
     }
 
-    let mut root = if let Some(path) = args.input_file {
-        let file = fs::File::open(&path)?;
-        serde_json::from_reader(&file)
-    } else {
-        serde_json::from_reader(io::stdin())
+    if args.dump_rules {
+        let docs = docs::rule_docs(&Registry::default(), settings);
+        match args.docs_format.as_str() {
+            "html" => println!("{}", docs::html(&docs)),
+            "json" => println!(
+                "{}",
+                serde_json::to_string(&docs).expect("Could not serialize documentation!")
+            ),
+            _ => println!("{}", docs::markdown(&docs)),
+        }
+        process::exit(0);
     }
-    .expect("Error reading input:");
 
-    root = normalize(root, &settings).expect("Input normalization error:");
+    if let Some(path) = args.media_manifest {
+        let manifest = fs::read_to_string(&path)?;
+        settings.media_files = Some(
+            manifest
+                .lines()
+                .map(|l| l.trim().replace('_', " "))
+                .filter(|l| !l.is_empty())
+                .collect(),
+        );
+    }
+
+    match args.command {
+        Some(Command::Explain { code }) => {
+            let docs = docs::rule_docs(&Registry::default(), settings);
+            match docs::code_doc(&docs, &code) {
+                Some(doc) => println!("{}", docs::kind_markdown(doc, 1)),
+                None => println!(
+                    "# {}\n\nThere is no documentation for this lint code.",
+                    code
+                ),
+            }
+            process::exit(0);
+        }
+        Some(Command::Stats { project, files }) => {
+            let mut linter = Linter::new(settings);
+            linter.strict = args.strict_internal;
+            print_stats(&collect_stats(&linter, project, &files)?);
+            process::exit(0);
+        }
+        Some(Command::Query { selector, files }) => {
+            for path in &files {
+                let root = read_document(path, &settings)?;
+                for element in select(&root, &selector) {
+                    let position = element.get_position();
+                    println!(
+                        "{}:{}:{}-{}:{}: {:?}",
+                        path.display(),
+                        position.start.line,
+                        position.start.col,
+                        position.end.line,
+                        position.end.col,
+                        ElementType::from(element)
+                    );
+                }
+            }
+            process::exit(0);
+        }
+        Some(Command::Ast {
+            raw,
+            json,
+            lints,
+            file,
+            ..
+        }) => {
+            let mut fired = vec![];
+            let root = if raw {
+                read_raw_document(&file)?
+            } else if lints {
+                // read like the lints of a file, with parse errors of sections.
+                let title = file
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let article = read_article(&file, &title, &settings)?;
+                let mut linter = Linter::new(settings);
+                linter.strict = args.strict_internal;
+                let report = linter.lint_article(&article);
+                check_errors(&report, args.strict_internal);
+                for (rule, rule_lints) in report.rule_lints {
+                    for lint in rule_lints {
+                        fired.push((rule.clone(), lint));
+                    }
+                }
+                for lint in &article.lints {
+                    fired.push(("Linter".to_string(), lint.clone()));
+                }
+                article.root
+            } else {
+                read_document(&file, &settings)?
+            };
+            if json {
+                let tree = serde_json::to_value(&root).expect("Could not serialize tree!");
+                let output = if lints {
+                    let fired: Vec<_> = fired
+                        .iter()
+                        .map(|(rule, lint)| json!({ "rule": rule, "lint": lint }))
+                        .collect();
+                    json!({ "tree": tree, "lints": fired })
+                } else {
+                    tree
+                };
+                println!("{}", output);
+            } else {
+                print!("{}", outline(&root, &fired));
+            }
+            process::exit(0);
+        }
+        None => (),
+    }
+
+    let mut linter = Linter::new(settings);
+    linter.strict = args.strict_internal;
+    linter.timings = args.timings;
+    let settings = &linter.settings;
+    let mut timings = BTreeMap::new();
+
+    if let Some(path) = args.project {
+        let project = Project::from_dir(&path, settings)?;
+        let mut lints = vec![];
+        for article in &project.articles {
+            let report = linter.lint_article(article);
+            check_errors(&report, args.strict_internal);
+            add_timings(&mut timings, &report);
+            for lint in report.lints {
+                lints.push(ArticleLint {
+                    article: article.title.clone(),
+                    lint,
+                });
+            }
+        }
+        lints.append(&mut check_project(&project, settings));
 
-    let mut rules = get_rules();
-    let mut lints = vec![];
+        let report: Vec<_> = lints
+            .iter()
+            .map(|l| (Some(l.article.as_str()), &l.lint))
+            .collect();
+        print_report(&linter, &report, &args.examples);
+        print_timings(&timings);
 
-    for mut rule in &mut rules {
-        rule.run(&root, &settings, &mut vec![])
-            .expect("error while checking rule:");
-        lints.append(&mut rule.lints().clone())
+        println!(
+            "{}",
+            &serde_json::to_string(&lints).expect("could not serialize lints:")
+        );
+        return Ok(());
     }
 
-    for lint in &lints {
-        eprintln!("{}", lint);
-        eprintln!("Examples:");
-        let examples = get_examples(&rules, lint.kind);
-        for example in examples {
-            eprintln!("{}", example);
+    // markup which can not be parsed is checked section by section.
+    if let Some(ref path) = args.input_file {
+        if path.extension().map(|e| e != "json").unwrap_or(true) {
+            let title = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let article = read_article(path, &title, settings)?;
+            let report = linter.lint_article(&article);
+            check_errors(&report, args.strict_internal);
+            add_timings(&mut timings, &report);
+            print_timings(&timings);
+            print_lints(&linter, &report.lints, &args.examples);
+            return Ok(());
         }
     }
 
-    println!(
-        "{}",
-        &serde_json::to_string(&lints).expect("could not serialize lints:")
-    );
+    let mut root = if let Some(path) = args.input_file {
+        let file = fs::File::open(&path)?;
+        serde_json::from_reader(&file)
+    } else {
+        serde_json::from_reader(io::stdin())
+    }
+    .expect("Error reading input:");
+
+    root = normalize(root, settings).expect("Input normalization error:");
+
+    let report = linter.lint_element(&root);
+    check_errors(&report, args.strict_internal);
+    add_timings(&mut timings, &report);
+    print_timings(&timings);
+    print_lints(&linter, &report.lints, &args.examples);
     Ok(())
 }
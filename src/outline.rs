@@ -0,0 +1,131 @@
+use crate::lint::Lint;
+use crate::rule::ElementType;
+use crate::utils::collect_elements;
+use mediawiki_parser::*;
+use mwparser_utils::extract_plain_text;
+use std::fmt::Write;
+use std::io;
+use std::ptr;
+
+fn contains(outer: &Span, inner: &Span) -> bool {
+    (outer.start.line, outer.start.col) <= (inner.start.line, inner.start.col)
+        && (inner.end.line, inner.end.col) <= (outer.end.line, outer.end.col)
+}
+
+// short description of an element.
+fn label(element: &Element) -> String {
+    let position = element.get_position();
+    let mut result = format!(
+        "{:?} {}:{}-{}:{}",
+        ElementType::from(element),
+        position.start.line,
+        position.start.col,
+        position.end.line,
+        position.end.col
+    );
+    let detail = match *element {
+        Element::Heading(ref heading) => format!("depth {}", heading.depth),
+        Element::Text(ref text) => format!("{:?}", text.text),
+        Element::Formatted(ref formatted) => format!("{:?}", formatted.markup),
+        Element::Template(ref template) => {
+            format!("{:?}", extract_plain_text(&template.name).trim())
+        }
+        Element::TemplateArgument(ref arg) => format!("{:?}", arg.name),
+        Element::InternalReference(ref link) => format!("{:?}", extract_plain_text(&link.target)),
+        Element::ExternalReference(ref link) => format!("{:?}", link.target),
+        Element::HtmlTag(ref tag) => format!("<{}>", tag.name),
+        Element::Comment(ref comment) => format!("{:?}", comment.text),
+        _ => return result,
+    };
+    result.push(' ');
+    result.push_str(&detail);
+    result
+}
+
+/// Prints elements indented by their depth.
+struct Printer<'e, 'l> {
+    path: Vec<&'e Element>,
+    out: String,
+    /// Lints with the rule producing them and the element they belong to.
+    lints: Vec<(&'l str, &'l Lint, Option<&'e Element>)>,
+}
+
+impl<'e, 'l> Traversion<'e, ()> for Printer<'e, 'l> {
+    path_impl!();
+
+    fn work(&mut self, root: &'e Element, _: (), _: &mut io::Write) -> io::Result<bool> {
+        let indent = "  ".repeat(self.path.len().saturating_sub(1));
+        writeln!(self.out, "{}{}", indent, label(root)).unwrap();
+        for &(rule, lint, element) in &self.lints {
+            if element.map_or(false, |e| ptr::eq(e, root)) {
+                writeln!(
+                    self.out,
+                    "{}  ! {} {}: {}",
+                    indent,
+                    rule,
+                    lint.code(),
+                    lint.explanation
+                )
+                .unwrap();
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Render a syntax tree as indented text with element types, spans and
+/// names. Lints (with the name of the rule producing them) are shown below
+/// the innermost element containing them.
+pub fn outline(root: &Element, lints: &[(String, Lint)]) -> String {
+    let elements = collect_elements(root, |_| true);
+    let lints = lints
+        .iter()
+        .map(|(rule, lint)| {
+            // elements are in pre-order, so the last one is the innermost.
+            let element = elements
+                .iter()
+                .rev()
+                .find(|e| contains(e.get_position(), &lint.position))
+                .cloned();
+            (rule.as_str(), lint, element)
+        })
+        .collect();
+    let mut printer = Printer {
+        path: vec![],
+        out: String::new(),
+        lints,
+    };
+    // the printer itself never fails.
+    printer
+        .run(root, (), &mut vec![])
+        .expect("printing the tree failed!");
+    printer.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{LintKind, Severity};
+
+    #[test]
+    fn tree_outline() {
+        let root = parse("== A ==\n{{Formel|<math>x</math>}}\n").unwrap();
+        let headings = collect_elements(&root, |e| ElementType::from(e) == ElementType::Heading);
+        let lint = Lint {
+            position: headings[0].get_position().clone(),
+            explanation: "A lint.".into(),
+            explanation_long: String::new(),
+            solution: String::new(),
+            severity: Severity::Info,
+            kind: LintKind::Custom,
+            replacements: vec![],
+            code: Some("test::lint".into()),
+        };
+        let text = outline(&root, &[("TestRule".into(), lint)]);
+        assert!(text.starts_with("Document 1:1-"));
+        assert!(text.contains("\n  Heading 1:1-"));
+        assert!(text.contains("Template 2:1-"));
+        assert!(text.contains("\"Formel\"\n"));
+        assert!(text.contains("depth 2\n    ! TestRule test::lint: A lint.\n"));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::lint::{Example, Lint, LintKind, Severity};
+use crate::rule::{Context, DocumentHooks, ElementType, Rule};
+use crate::rules::get_rules;
+use crate::settings::{RuleMeta, Settings};
+use mediawiki_parser::*;
+use std::io;
+
+/// Metadata of a lint code of a plugin rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintCode {
+    /// Namespaced code like `project::no-noprint`.
+    pub code: String,
+    /// Explanation of the lint.
+    pub description: String,
+    /// Default severity.
+    pub severity: Severity,
+    /// Bad and good examples. Their kind is `Custom` and their code is set
+    /// to this code when the rule is registered.
+    #[serde(default)]
+    pub examples: Vec<Example>,
+}
+
+impl LintCode {
+    /// Create a lint of this code.
+    pub fn lint(&self, position: &Span, explanation: String, solution: String) -> Lint {
+        Lint {
+            position: position.clone(),
+            explanation,
+            explanation_long: self.description.clone(),
+            solution,
+            severity: self.severity.clone(),
+            kind: LintKind::Custom,
+            replacements: vec![],
+            code: Some(self.code.clone()),
+        }
+    }
+}
+
+/// Is this lint code namespaced (`ns::name`)?
+pub fn is_namespaced(code: &str) -> bool {
+    let mut parts = code.splitn(2, "::");
+    match (parts.next(), parts.next()) {
+        (Some(namespace), Some(name)) => !namespace.is_empty() && !name.is_empty(),
+        _ => false,
+    }
+}
+
+/// A rule provided by another crate.
+///
+/// Unlike `Rule`, this trait does not depend on the lifetime of the checked
+/// document, so plugin rules can be created by a plain constructor function.
+pub trait PluginRule {
+    fn meta(&self) -> RuleMeta;
+    /// The lint codes this rule may emit.
+    fn codes(&self) -> Vec<LintCode>;
+    /// Element types this rule wants to check, `None` for all.
+    fn interests(&self) -> Option<&'static [ElementType]> {
+        None
+    }
+    /// Optional rules only run if they are listed in `optional_rules`.
+    fn optional(&self) -> bool {
+        false
+    }
+    fn begin_document(&mut self, _context: &Context) -> io::Result<Vec<Lint>> {
+        Ok(vec![])
+    }
+    /// Check an element. `path` contains its ancestors and the element itself.
+    fn check(
+        &mut self,
+        element: &Element,
+        path: &[&Element],
+        settings: &Settings,
+    ) -> io::Result<Vec<Lint>>;
+    fn end_document(&mut self, _context: &Context) -> io::Result<Vec<Lint>> {
+        Ok(vec![])
+    }
+}
+
+/// Creates a new instance of a plugin rule for every document.
+pub type PluginConstructor = fn() -> Box<PluginRule>;
+
+/// Runs a plugin rule as a `Rule`.
+struct PluginAdapter<'e> {
+    rule: Box<PluginRule>,
+    path: Vec<&'e Element>,
+    lints: Vec<Lint>,
+    examples: Vec<Example>,
+}
+
+impl<'e> PluginAdapter<'e> {
+    fn new(rule: Box<PluginRule>) -> Self {
+        let mut examples = vec![];
+        for code in rule.codes() {
+            for mut example in code.examples {
+                example.kind = LintKind::Custom;
+                example.code = Some(code.code.clone());
+                examples.push(example);
+            }
+        }
+        PluginAdapter {
+            rule,
+            path: vec![],
+            lints: vec![],
+            examples,
+        }
+    }
+}
+
+impl<'e, 's> Traversion<'e, &'s Settings<'s>> for PluginAdapter<'e> {
+    path_impl!();
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        let mut lints = self.rule.check(root, &self.path, settings)?;
+        self.lints.append(&mut lints);
+        Ok(true)
+    }
+}
+
+impl<'e, 's: 'e> DocumentHooks<'e, 's> for PluginAdapter<'e> {
+    fn begin_document(&mut self, context: &Context<'e, 's>) -> io::Result<()> {
+        let mut lints = self.rule.begin_document(context)?;
+        self.lints.append(&mut lints);
+        Ok(())
+    }
+
+    fn end_document(&mut self, context: &Context<'e, 's>) -> io::Result<()> {
+        let mut lints = self.rule.end_document(context)?;
+        self.lints.append(&mut lints);
+        Ok(())
+    }
+}
+
+impl<'e, 's: 'e> Rule<'e, 's> for PluginAdapter<'e> {
+    fn meta(&self) -> RuleMeta {
+        self.rule.meta()
+    }
+
+    fn push(&mut self, lint: Lint) {
+        self.lints.push(lint);
+    }
+
+    fn lints(&self) -> &Vec<Lint> {
+        &self.lints
+    }
+
+    fn examples(&self) -> &Vec<Example> {
+        &self.examples
+    }
+
+    fn interests(&self) -> Option<&'static [ElementType]> {
+        self.rule.interests()
+    }
+
+    fn optional(&self) -> bool {
+        self.rule.optional()
+    }
+
+    fn path_mut(&mut self) -> &mut Vec<&'e Element> {
+        &mut self.path
+    }
+}
+
+/// The built-in rules and rules registered by other crates.
+#[derive(Default)]
+pub struct Registry {
+    plugins: Vec<PluginConstructor>,
+    codes: Vec<LintCode>,
+}
+
+impl Registry {
+    /// Register a plugin rule. Its lint codes must be namespaced (`ns::name`)
+    /// and must not be registered already.
+    pub fn register(&mut self, constructor: PluginConstructor) -> Result<(), String> {
+        let codes = constructor().codes();
+        for code in &codes {
+            if !is_namespaced(&code.code) {
+                return Err(format!(
+                    "lint code `{}` must have the form `namespace::name`!",
+                    code.code
+                ));
+            }
+            if self.code(&code.code).is_some() {
+                return Err(format!("lint code `{}` is already registered!", code.code));
+            }
+        }
+        self.plugins.push(constructor);
+        self.codes.extend(codes);
+        Ok(())
+    }
+
+    /// Lint codes of the registered plugin rules.
+    pub fn codes(&self) -> &[LintCode] {
+        &self.codes
+    }
+
+    /// Find a lint code of a plugin rule.
+    pub fn code(&self, code: &str) -> Option<&LintCode> {
+        self.codes.iter().find(|c| c.code == code)
+    }
+
+    /// New instances of all built-in and registered rules, including the
+    /// optional rules (e.g. for documentation).
+    pub fn all_rules<'e, 's: 'e>(&self) -> Vec<Box<Rule<'e, 's>>> {
+        let mut rules = get_rules();
+        for constructor in &self.plugins {
+            rules.push(Box::new(PluginAdapter::new(constructor())));
+        }
+        rules
+    }
+
+    /// New instances of all built-in and registered rules, without the
+    /// optional rules not enabled in the settings.
+    pub fn rules<'e, 's: 'e>(&self, settings: &Settings) -> Vec<Box<Rule<'e, 's>>> {
+        let mut rules = self.all_rules();
+        rules.retain(|r| !r.optional() || settings.optional_rules.contains(&r.meta().name));
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docs;
+    use crate::linter::Linter;
+
+    struct NoComments;
+
+    fn code() -> LintCode {
+        LintCode {
+            code: "test::no-comments".into(),
+            description: "Comments are not allowed.".into(),
+            severity: Severity::Info,
+            examples: vec![Example {
+                name: "comment".into(),
+                bad: "Text <!-- a comment -->".into(),
+                good: "Text".into(),
+                bad_explanation: "The text contains a comment.".into(),
+                good_explanation: "There is no comment.".into(),
+                kind: LintKind::Custom,
+                code: None,
+            }],
+        }
+    }
+
+    impl PluginRule for NoComments {
+        fn meta(&self) -> RuleMeta {
+            RuleMeta {
+                name: "NoComments".into(),
+                description: "Forbids comments.".into(),
+            }
+        }
+
+        fn codes(&self) -> Vec<LintCode> {
+            vec![code()]
+        }
+
+        fn interests(&self) -> Option<&'static [ElementType]> {
+            Some(&[ElementType::Comment])
+        }
+
+        fn check(
+            &mut self,
+            element: &Element,
+            _: &[&Element],
+            _: &Settings,
+        ) -> io::Result<Vec<Lint>> {
+            Ok(vec![code().lint(
+                element.get_position(),
+                "This is a comment.".into(),
+                "Remove it.".into(),
+            )])
+        }
+    }
+
+    fn no_comments() -> Box<PluginRule> {
+        Box::new(NoComments)
+    }
+
+    struct Crash;
+
+    impl PluginRule for Crash {
+        fn meta(&self) -> RuleMeta {
+            RuleMeta {
+                name: "Crash".into(),
+                description: "Panics on every template.".into(),
+            }
+        }
+
+        fn codes(&self) -> Vec<LintCode> {
+            vec![]
+        }
+
+        fn interests(&self) -> Option<&'static [ElementType]> {
+            Some(&[ElementType::Template])
+        }
+
+        fn check(&mut self, _: &Element, _: &[&Element], _: &Settings) -> io::Result<Vec<Lint>> {
+            panic!("crashed")
+        }
+    }
+
+    fn crash() -> Box<PluginRule> {
+        Box::new(Crash)
+    }
+
+    #[test]
+    fn plugin_rule() {
+        let mut linter = Linter::new(Settings::default());
+        linter.registry.register(no_comments).unwrap();
+        assert!(linter.registry.register(no_comments).is_err());
+
+        let report = linter.lint_str("Text <!-- a comment -->").unwrap();
+        let lint = report
+            .lints
+            .iter()
+            .find(|l| l.kind == LintKind::Custom)
+            .unwrap();
+        assert_eq!(lint.code(), "test::no-comments");
+    }
+
+    #[test]
+    fn plugin_docs() {
+        let mut registry = Registry::default();
+        registry.register(no_comments).unwrap();
+        let docs = docs::rule_docs(&registry, Settings::default());
+        let doc = docs::code_doc(&docs, "test::no-comments").unwrap();
+        assert_eq!(doc.severity, Some(Severity::Info));
+        assert_eq!(doc.examples[0].code(), "test::no-comments");
+        assert!(docs::markdown(&docs).contains("### test::no-comments\n"));
+    }
+
+    #[test]
+    fn rule_panic() {
+        let mut linter = Linter::new(Settings::default());
+        linter.registry.register(no_comments).unwrap();
+        linter.registry.register(crash).unwrap();
+
+        let report = linter
+            .lint_str("{{Formel|<math>x</math>}} <!-- a comment -->")
+            .unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].rule, "Crash");
+        assert!(report.errors[0].message.contains("crashed"));
+        let internal = report
+            .lints
+            .iter()
+            .find(|l| l.kind == LintKind::InternalError);
+        assert_eq!(
+            internal.unwrap().position.start,
+            report.errors[0].position.clone().unwrap().start
+        );
+        assert!(report.lints.iter().any(|l| l.kind == LintKind::Custom));
+    }
+}
@@ -0,0 +1,536 @@
+use crate::lint::{Lint, LintKind, Severity};
+use crate::linter::parse_sections;
+use crate::settings::Settings;
+use crate::utils::*;
+use mediawiki_parser::*;
+use mwparser_utils::extract_plain_text;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A single article of a project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    /// The page title of the article.
+    pub title: String,
+    /// The (normalized) syntax tree of the article.
+    pub root: Element,
+    /// Lints found while reading the article, like `ParseError`s.
+    pub lints: Vec<Lint>,
+}
+
+/// A set of articles which are checked against each other.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Project {
+    pub articles: Vec<Article>,
+}
+
+/// A lint found in a specific article of a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "lowercase")]
+pub struct ArticleLint {
+    /// Title of the article the lint was found in.
+    pub article: String,
+    /// The lint itself.
+    pub lint: Lint,
+}
+
+fn invalid_data<E: ToString>(path: &Path, error: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), error.to_string()),
+    )
+}
+
+/// Read a document from a file without normalizing it. Files ending in
+/// `.json` are read as syntax trees, all other files are parsed as
+/// MediaWiki markup.
+pub fn read_raw_document(path: &Path) -> io::Result<Element> {
+    if path.extension().map(|e| e == "json").unwrap_or(false) {
+        let file = fs::File::open(path)?;
+        serde_json::from_reader(&file).map_err(|e| invalid_data(path, e))
+    } else {
+        let source = fs::read_to_string(path)?;
+        parse(&source).map_err(|e| invalid_data(path, e))
+    }
+}
+
+/// Read a (normalized) document from a file, see `read_raw_document`.
+pub fn read_document(path: &Path, settings: &Settings) -> io::Result<Element> {
+    let root = read_raw_document(path)?;
+    crate::normalize(root, settings).map_err(|e| invalid_data(path, e))
+}
+
+/// Read an article from a file like `read_document`. MediaWiki markup which
+/// can not be parsed is read without the sections which fail, they are kept
+/// as lints of the article (see `parse_sections`).
+pub fn read_article(path: &Path, title: &str, settings: &Settings) -> io::Result<Article> {
+    let (root, lints) = if path.extension().map(|e| e == "json").unwrap_or(false) {
+        (read_raw_document(path)?, vec![])
+    } else {
+        let source = fs::read_to_string(path)?;
+        parse_sections(&source).map_err(|e| invalid_data(path, e))?
+    };
+    Ok(Article {
+        title: normalize_title(title),
+        root: crate::normalize(root, settings).map_err(|e| invalid_data(path, e))?,
+        lints,
+    })
+}
+
+impl Project {
+    /// Add an article to this project.
+    pub fn add(&mut self, title: &str, root: Element) {
+        self.articles.push(Article {
+            title: normalize_title(title),
+            root,
+            lints: vec![],
+        });
+    }
+
+    /// Find an article by its page title.
+    pub fn find(&self, title: &str) -> Option<&Article> {
+        let title = normalize_title(title);
+        self.articles.iter().find(|a| a.title == title)
+    }
+
+    /// Read all articles in a directory (recursively).
+    ///
+    /// Files ending in `.json` are read as syntax trees, all other files are
+    /// parsed as MediaWiki markup. The page title of an article is its path
+    /// relative to the directory without extension, so subpages are stored
+    /// in subdirectories. Markup which can not be parsed is read section by
+    /// section, see `read_article`.
+    pub fn from_dir(path: &Path, settings: &Settings) -> io::Result<Project> {
+        let mut project = Project::default();
+        project.read_dir(path, path, settings)?;
+        project.articles.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(project)
+    }
+
+    fn read_dir(&mut self, base: &Path, dir: &Path, settings: &Settings) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.read_dir(base, &path, settings)?;
+                continue;
+            }
+
+            let title = path
+                .strip_prefix(base)
+                .unwrap_or(&path)
+                .with_extension("")
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+                .join("/");
+            self.articles.push(read_article(&path, &title, settings)?);
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a link target relative to an article to the title of the linked
+/// article and an optional anchor. Returns `None` for pages which are not
+/// part of the project.
+pub fn resolve_target(
+    current: &str,
+    target: &str,
+    settings: &Settings,
+) -> Option<(String, Option<String>)> {
+    let target = target.trim().trim_start_matches(':');
+    let (page, anchor) = match target.find('#') {
+        Some(index) => (&target[..index], Some(normalize_anchor(&target[index + 1..]))),
+        None => (target, None),
+    };
+    let page = page.trim();
+
+    let title = if page.is_empty() {
+        current.to_string()
+    } else if page.starts_with('/') {
+        normalize_title(&format!("{}{}", current, page.trim_end_matches('/')))
+    } else {
+        let page = normalize_title(page);
+        let prefix = normalize_title(&settings.page_prefix);
+        if prefix.is_empty() {
+            // pages in other namespaces are not part of the project.
+            if page.contains(':') {
+                return None;
+            }
+            page
+        } else if page.starts_with(&prefix) {
+            normalize_title(&page[prefix.len()..])
+        } else {
+            return None;
+        }
+    };
+    Some((title, anchor))
+}
+
+/// Anchor names are compared with underscores as spaces.
+fn normalize_anchor(anchor: &str) -> String {
+    anchor
+        .replace('_', " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+pub(crate) fn missing_section_target(
+    position: &Span,
+    article: &str,
+    section: &str,
+    suggestions: &[&str],
+) -> Lint {
+    let solution = if suggestions.is_empty() {
+        format!(
+            "Add `<section begin=\"{}\" />` and `<section end=\"{}\" />` to `{}` \
+             or include another section.",
+            section, section, article
+        )
+    } else {
+        format!(
+            "Did you mean {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<String>>()
+                .join(" or ")
+        )
+    };
+    Lint {
+        position: position.clone(),
+        explanation: format!(
+            "The article `{}` has no section named `{}`!",
+            article, section
+        ),
+        explanation_long: "`{{#lst:<article name>|<section name>}}` includes the part of \
+                           an article between `<section begin=\"<section name>\" />` and \
+                           `<section end=\"<section name>\" />`. If there is no such section, \
+                           nothing is included."
+            .into(),
+        solution,
+        severity: Severity::Error,
+        kind: LintKind::MissingSectionTarget,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+// names of all sections defined in an article.
+fn section_names(root: &Element) -> Vec<&str> {
+    collect_tags(root, "section")
+        .into_iter()
+        .filter_map(|tag| tag_attribute(tag, "begin"))
+        .collect()
+}
+
+/// Verify that sections included with `{{#lst:...}}` exist in their article.
+pub fn check_section_targets(project: &Project, settings: &Settings) -> Vec<ArticleLint> {
+    let mut result = vec![];
+    for article in &project.articles {
+        let transclusions = collect_elements(&article.root, |e| {
+            if let Element::Template(ref template) = *e {
+                extract_plain_text(&template.name)
+                    .trim()
+                    .to_lowercase()
+                    .starts_with("#lst:")
+            } else {
+                false
+            }
+        });
+
+        for element in transclusions {
+            let template = if let Element::Template(ref template) = *element {
+                template
+            } else {
+                continue;
+            };
+            let name = extract_plain_text(&template.name);
+            let target_name = &name.trim()["#lst:".len()..];
+            let section = template
+                .content
+                .first()
+                .and_then(|c| {
+                    if let Element::TemplateArgument(ref v) = *c {
+                        Some(extract_plain_text(&v.value).trim().to_string())
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_default();
+
+            // titles are looked up as written first, then like links with
+            // the page prefix. unknown articles and malformed sections are
+            // reported elsewhere.
+            let target = match project.find(target_name).or_else(|| {
+                resolve_target(&article.title, target_name, settings)
+                    .and_then(|(title, _)| project.find(&title))
+            }) {
+                Some(target) => target,
+                None => continue,
+            };
+            if section.is_empty() {
+                continue;
+            }
+
+            let sections = section_names(&target.root);
+            if !sections.contains(&section.as_str()) {
+                let suggestions = closest_matches(&section, sections.iter().cloned());
+                result.push(ArticleLint {
+                    article: article.title.clone(),
+                    lint: missing_section_target(
+                        &template.position,
+                        &target.title,
+                        &section,
+                        &suggestions,
+                    ),
+                });
+            }
+        }
+    }
+    result
+}
+
+pub(crate) fn broken_link(position: &Span, title: &str, suggestions: &[&str]) -> Lint {
+    let solution = if suggestions.is_empty() {
+        "Correct the link target or remove the link.".to_string()
+    } else {
+        format!(
+            "Did you mean {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<String>>()
+                .join(" or ")
+        )
+    };
+    Lint {
+        position: position.clone(),
+        explanation: format!("The page `{}` does not exist in this project!", title),
+        explanation_long: "This link points to a page of the project which does not \
+                           exist. Maybe the page was renamed or the link contains a \
+                           spelling mistake."
+            .into(),
+        solution,
+        severity: Severity::Error,
+        kind: LintKind::BrokenInternalLink,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+pub(crate) fn missing_anchor(
+    position: &Span,
+    title: &str,
+    anchor: &str,
+    suggestions: &[&str],
+) -> Lint {
+    let solution = if suggestions.is_empty() {
+        format!("Link to an existing heading of `{}`.", title)
+    } else {
+        format!(
+            "Did you mean {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("`#{}`", s))
+                .collect::<Vec<String>>()
+                .join(" or ")
+        )
+    };
+    Lint {
+        position: position.clone(),
+        explanation: format!("The page `{}` has no heading `{}`!", title, anchor),
+        explanation_long: "A link of the form `[[page#anchor]]` points to the heading \
+                           `anchor` of `page`. If this heading does not exist, the link \
+                           only leads to the top of the page."
+            .into(),
+        solution,
+        severity: Severity::Warning,
+        kind: LintKind::MissingAnchor,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+pub(crate) fn self_link(position: &Span) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: "This page links to itself.".into(),
+        explanation_long: "A link to the page it is contained in does not lead anywhere \
+                           and is rendered as bold text by MediaWiki."
+            .into(),
+        solution: "Remove the link or link to a heading with `[[#heading]]`.".into(),
+        severity: Severity::Warning,
+        kind: LintKind::SelfLink,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+// anchors of all headings of an article.
+fn heading_anchors(root: &Element) -> Vec<String> {
+    collect_elements(root, |e| {
+        if let Element::Heading(_) = *e {
+            true
+        } else {
+            false
+        }
+    })
+    .into_iter()
+    .filter_map(|e| {
+        if let Element::Heading(ref heading) = *e {
+            Some(normalize_anchor(&extract_plain_text(&heading.caption)))
+        } else {
+            None
+        }
+    })
+    .collect()
+}
+
+/// Verify internal links between the articles of a project.
+pub fn check_links(project: &Project, settings: &Settings) -> Vec<ArticleLint> {
+    let mut result = vec![];
+    for article in &project.articles {
+        let links = collect_elements(&article.root, |e| {
+            if let Element::InternalReference(_) = *e {
+                true
+            } else {
+                false
+            }
+        });
+
+        for element in links {
+            let link = if let Element::InternalReference(ref link) = *element {
+                link
+            } else {
+                continue;
+            };
+            let target = extract_plain_text(&link.target);
+            let (title, anchor) = match resolve_target(&article.title, &target, settings) {
+                Some(resolved) => resolved,
+                None => continue,
+            };
+
+            let is_anchor_link = target.trim().starts_with('#');
+            if title == article.title && anchor.is_none() && !is_anchor_link {
+                result.push(ArticleLint {
+                    article: article.title.clone(),
+                    lint: self_link(&link.position),
+                });
+                continue;
+            }
+
+            let linked = match project.find(&title) {
+                Some(linked) => linked,
+                None => {
+                    let suggestions = closest_matches(
+                        &title,
+                        project.articles.iter().map(|a| a.title.as_str()),
+                    );
+                    result.push(ArticleLint {
+                        article: article.title.clone(),
+                        lint: broken_link(&link.position, &title, &suggestions),
+                    });
+                    continue;
+                }
+            };
+
+            if let Some(anchor) = anchor {
+                let anchors = heading_anchors(&linked.root);
+                if !anchors.contains(&anchor) {
+                    let suggestions =
+                        closest_matches(&anchor, anchors.iter().map(|a| a.as_str()));
+                    result.push(ArticleLint {
+                        article: article.title.clone(),
+                        lint: missing_anchor(&link.position, &title, &anchor, &suggestions),
+                    });
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Run all checks which need knowledge of the whole project.
+pub fn check_project(project: &Project, settings: &Settings) -> Vec<ArticleLint> {
+    let mut result = vec![];
+    result.append(&mut check_section_targets(project, settings));
+    result.append(&mut check_links(project, settings));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(articles: &[(&str, &str)]) -> Project {
+        let mut project = Project::default();
+        for (title, source) in articles {
+            project.add(title, parse(source).unwrap());
+        }
+        project
+    }
+
+    #[test]
+    fn missing_section_target() {
+        let project = project(&[
+            ("Main", "{{#lst:Proofs|prof}}"),
+            ("Proofs", "<section begin=\"proof\" />Text<section end=\"proof\" />"),
+        ]);
+        let lints = check_section_targets(&project, &Settings::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].article, "Main");
+        assert_eq!(lints[0].lint.kind, LintKind::MissingSectionTarget);
+        assert!(lints[0].lint.solution.contains("`proof`"));
+    }
+
+    #[test]
+    fn existing_section_target() {
+        let project = project(&[
+            ("Main", "{{#lst:proofs|proof}}"),
+            ("Proofs", "<section begin=\"proof\" />Text<section end=\"proof\" />"),
+        ]);
+        assert!(check_section_targets(&project, &Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn prefixed_section_target() {
+        let project = project(&[
+            ("Main", "{{#lst:Mathe für Nicht-Freaks: Proofs|prof}}"),
+            ("Proofs", "<section begin=\"proof\" />Text<section end=\"proof\" />"),
+        ]);
+        let lints = check_section_targets(&project, &Settings::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].lint.kind, LintKind::MissingSectionTarget);
+    }
+
+    #[test]
+    fn link_checks() {
+        let project = project(&[
+            (
+                "Main",
+                "== Intro ==\n\
+                 [[Mathe für Nicht-Freaks: Proofs#Induction]] \
+                 [[Mathe für Nicht-Freaks: Prof]] \
+                 [[Mathe für Nicht-Freaks: Proofs#Inducton]] \
+                 [[Mathe für Nicht-Freaks: Main]] \
+                 [[#Intro]] \
+                 [[Other book]]",
+            ),
+            ("Proofs", "== Induction ==\nText"),
+        ]);
+        let kinds: Vec<LintKind> = check_links(&project, &Settings::default())
+            .into_iter()
+            .map(|l| l.lint.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LintKind::BrokenInternalLink,
+                LintKind::MissingAnchor,
+                LintKind::SelfLink,
+            ]
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use crate::rule::ElementType;
+use mediawiki_parser::*;
+use mwparser_utils::extract_plain_text;
+use regex::Regex;
+use std::io;
+use std::slice;
+use std::str::FromStr;
+
+/// How a step of a selector relates to the previous step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    /// `a b`: `b` is somewhere inside of `a`.
+    Descendant,
+    /// `a > b`: `b` is directly inside of `a`. Template arguments are
+    /// skipped, so `template > list` finds lists in argument values.
+    Child,
+}
+
+/// Comparison of an attribute value.
+#[derive(Debug, Clone)]
+enum Match {
+    /// `=`, compared case-insensitively.
+    Exact(String),
+    /// `~=`, a regular expression.
+    Pattern(Regex),
+}
+
+impl Match {
+    fn matches(&self, value: &str) -> bool {
+        match *self {
+            Match::Exact(ref expected) => value.trim().to_lowercase() == *expected,
+            Match::Pattern(ref regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Condition in square brackets.
+#[derive(Debug, Clone)]
+enum Filter {
+    /// Name of a template, template argument, html tag, markup or link target.
+    Name(Match),
+    /// Depth of a heading.
+    Depth(usize),
+    /// Plain text of the element.
+    Text(Match),
+}
+
+/// Conditions on a single element, like `template[name=formel]`.
+#[derive(Debug, Clone)]
+struct Compound {
+    /// `None` for `*`.
+    element: Option<ElementType>,
+    filters: Vec<Filter>,
+}
+
+fn element_name(element: &Element) -> Option<String> {
+    Some(match *element {
+        Element::Template(ref template) => extract_plain_text(&template.name),
+        Element::TemplateArgument(ref arg) => arg.name.clone(),
+        Element::HtmlTag(ref tag) => tag.name.clone(),
+        Element::Formatted(ref formatted) => format!("{:?}", formatted.markup).to_lowercase(),
+        Element::InternalReference(ref link) => extract_plain_text(&link.target),
+        Element::ExternalReference(ref link) => link.target.clone(),
+        _ => return None,
+    })
+}
+
+impl Compound {
+    fn matches(&self, element: &Element) -> bool {
+        if let Some(element_type) = self.element {
+            if ElementType::from(element) != element_type {
+                return false;
+            }
+        }
+        self.filters.iter().all(|filter| match *filter {
+            Filter::Name(ref m) => element_name(element).map_or(false, |n| m.matches(&n)),
+            Filter::Depth(depth) => match *element {
+                Element::Heading(ref heading) => heading.depth == depth,
+                _ => false,
+            },
+            Filter::Text(ref m) => m.matches(&extract_plain_text(slice::from_ref(element))),
+        })
+    }
+}
+
+/// A CSS-like selector over syntax tree elements.
+///
+/// Steps consist of an element type (lowercase, `argument` for template
+/// arguments or `*` for any) and filters: `[name=formel]`, `[depth=2]`,
+/// `[text~=regex]`. `=` compares case-insensitively, `~=` matches a regular
+/// expression. Steps are combined with ` ` (descendant) or `>` (child):
+///
+/// ```text
+/// template[name=formel] > argument[name=1] list
+/// ```
+#[derive(Debug, Clone)]
+pub struct Selector {
+    steps: Vec<(Combinator, Compound)>,
+}
+
+fn element_type(name: &str) -> Option<ElementType> {
+    Some(match name {
+        "document" => ElementType::Document,
+        "heading" => ElementType::Heading,
+        "text" => ElementType::Text,
+        "formatted" => ElementType::Formatted,
+        "paragraph" => ElementType::Paragraph,
+        "template" => ElementType::Template,
+        "argument" | "templateargument" => ElementType::TemplateArgument,
+        "internalreference" => ElementType::InternalReference,
+        "externalreference" => ElementType::ExternalReference,
+        "listitem" => ElementType::ListItem,
+        "list" => ElementType::List,
+        "table" => ElementType::Table,
+        "tablerow" => ElementType::TableRow,
+        "tablecell" => ElementType::TableCell,
+        "comment" => ElementType::Comment,
+        "htmltag" => ElementType::HtmlTag,
+        "gallery" => ElementType::Gallery,
+        "error" => ElementType::Error,
+        _ => return None,
+    })
+}
+
+fn parse_filter(source: &str) -> Result<Filter, String> {
+    let (key, regex, value) = if let Some(index) = source.find("~=") {
+        (&source[..index], true, &source[index + 2..])
+    } else if let Some(index) = source.find('=') {
+        (&source[..index], false, &source[index + 1..])
+    } else {
+        return Err(format!("filter `[{}]` needs a value!", source));
+    };
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    let value = if regex {
+        Match::Pattern(Regex::new(value).map_err(|e| e.to_string())?)
+    } else {
+        Match::Exact(value.trim().to_lowercase())
+    };
+    match (key.trim(), value) {
+        ("name", value) => Ok(Filter::Name(value)),
+        ("text", value) => Ok(Filter::Text(value)),
+        ("depth", Match::Exact(depth)) => depth
+            .parse()
+            .map(Filter::Depth)
+            .map_err(|_| format!("invalid heading depth `{}`!", depth)),
+        (key, _) => Err(format!("unknown filter `{}`!", key)),
+    }
+}
+
+fn parse_compound(source: &str) -> Result<Compound, String> {
+    let (name, mut rest) = match source.find('[') {
+        Some(index) => source.split_at(index),
+        None => (source, ""),
+    };
+    let element = match name {
+        "" | "*" => None,
+        name => Some(
+            element_type(&name.to_lowercase())
+                .ok_or_else(|| format!("unknown element type `{}`!", name))?,
+        ),
+    };
+    let mut filters = vec![];
+    while !rest.is_empty() {
+        let end = match (rest.starts_with('['), rest.find(']')) {
+            (true, Some(end)) => end,
+            _ => return Err(format!("malformed filter in `{}`!", source)),
+        };
+        filters.push(parse_filter(&rest[1..end])?);
+        rest = &rest[end + 1..];
+    }
+    Ok(Compound { element, filters })
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, String> {
+        // split into compounds and combinators, spaces in brackets or
+        // quotes do not separate steps.
+        let mut tokens = vec![];
+        let mut current = String::new();
+        let mut quote = None;
+        let mut in_brackets = false;
+        for c in source.chars() {
+            match (c, quote) {
+                ('"', None) | ('\'', None) if in_brackets => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                ('[', None) => in_brackets = true,
+                (']', None) => in_brackets = false,
+                (c, None) if !in_brackets && (c.is_whitespace() || c == '>') => {
+                    if !current.is_empty() {
+                        tokens.push(current.clone());
+                        current.clear();
+                    }
+                    if c == '>' {
+                        tokens.push(">".into());
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        let mut steps = vec![];
+        let mut combinator = Combinator::Descendant;
+        for token in tokens {
+            if token == ">" {
+                if steps.is_empty() || combinator == Combinator::Child {
+                    return Err(format!("misplaced `>` in `{}`!", source));
+                }
+                combinator = Combinator::Child;
+                continue;
+            }
+            steps.push((combinator, parse_compound(&token)?));
+            combinator = Combinator::Descendant;
+        }
+        if steps.is_empty() || combinator == Combinator::Child {
+            return Err(format!("incomplete selector `{}`!", source));
+        }
+        Ok(Selector { steps })
+    }
+}
+
+impl Selector {
+    /// Does the last element of a path (from the root) match?
+    pub fn matches(&self, path: &[&Element]) -> bool {
+        !path.is_empty() && self.match_step(self.steps.len() - 1, path, path.len() - 1)
+    }
+
+    fn match_step(&self, step: usize, path: &[&Element], index: usize) -> bool {
+        let (combinator, ref compound) = self.steps[step];
+        if !compound.matches(path[index]) {
+            return false;
+        }
+        if step == 0 {
+            return true;
+        }
+        match combinator {
+            Combinator::Descendant => (0..index).any(|i| self.match_step(step - 1, path, i)),
+            Combinator::Child => {
+                if index == 0 {
+                    return false;
+                }
+                let parent = index - 1;
+                self.match_step(step - 1, path, parent)
+                    || (parent > 0
+                        && ElementType::from(path[parent]) == ElementType::TemplateArgument
+                        && self.match_step(step - 1, path, parent - 1))
+            }
+        }
+    }
+}
+
+/// Collects the elements matching a selector.
+struct Finder<'e, 'q> {
+    path: Vec<&'e Element>,
+    found: Vec<&'e Element>,
+    selector: &'q Selector,
+}
+
+impl<'e, 'q> Traversion<'e, ()> for Finder<'e, 'q> {
+    path_impl!();
+
+    fn work(&mut self, root: &'e Element, _: (), _: &mut io::Write) -> io::Result<bool> {
+        if self.selector.matches(&self.path) {
+            self.found.push(root);
+        }
+        Ok(true)
+    }
+}
+
+/// Get all elements of a tree matching a selector in source order.
+pub fn select<'e>(root: &'e Element, selector: &Selector) -> Vec<&'e Element> {
+    let mut finder = Finder {
+        path: vec![],
+        found: vec![],
+        selector,
+    };
+    // the finder itself never fails.
+    finder
+        .run(root, (), &mut vec![])
+        .expect("selecting elements failed!");
+    finder.found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(selector: &str, source: &str) -> usize {
+        let selector: Selector = selector.parse().unwrap();
+        select(&parse(source).unwrap(), &selector).len()
+    }
+
+    #[test]
+    fn selectors() {
+        let source =
+            "== A ==\n{{Formel|<math>x</math>}}\n\n{{Beispiel|\n* a\n* b\n}}\n\n=== B ===\n* c\n";
+        assert_eq!(count("template", source), 2);
+        assert_eq!(count("template[name=formel]", source), 1);
+        assert_eq!(count("template[name~='^B'] > list", source), 1);
+        assert_eq!(count("template > argument[name=1] > list", source), 1);
+        assert_eq!(count("template listitem", source), 2);
+        assert_eq!(count("heading[depth=3]", source), 1);
+        assert_eq!(count("listitem[text~=c]", source), 1);
+        assert_eq!(count("list", source), 2);
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        for selector in &["", "> list", "list >", "foo", "list[depth]", "list[size=2]"] {
+            assert!(selector.parse::<Selector>().is_err(), "{}", selector);
+        }
+    }
+}
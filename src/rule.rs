@@ -3,16 +3,146 @@ use crate::settings::{RuleMeta, Settings};
 use mediawiki_parser::*;
 use std::io;
 
+/// Information about the checked document, available to all rules.
+pub struct Context<'e, 's: 'e> {
+    /// The syntax tree of the document.
+    pub root: &'e Element,
+    /// Page title of the document, if known.
+    pub title: Option<&'e str>,
+    /// Source text of the document, if known.
+    pub source: Option<&'e str>,
+    pub settings: &'s Settings<'s>,
+}
+
+impl<'e, 's: 'e> Context<'e, 's> {
+    pub fn new(root: &'e Element, settings: &'s Settings<'s>) -> Self {
+        Context {
+            root,
+            title: None,
+            source: None,
+            settings,
+        }
+    }
+}
+
+/// Hooks called before and after a rule traverses a document.
+pub trait DocumentHooks<'e, 's: 'e> {
+    fn begin_document(&mut self, _context: &Context<'e, 's>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called after the traversal, e.g. for checks of the whole document.
+    fn end_document(&mut self, _context: &Context<'e, 's>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Linter rule trait.
-pub trait Rule<'e, 's: 'e>: Traversion<'e, &'s Settings<'s>> + 'e {
+pub trait Rule<'e, 's: 'e>: Traversion<'e, &'s Settings<'s>> + DocumentHooks<'e, 's> + 'e {
     fn meta(&self) -> RuleMeta;
     fn push(&mut self, lint: Lint);
     fn lints(&self) -> &Vec<Lint>;
     fn examples(&self) -> &Vec<Example>;
+    /// Element types this rule wants to check, `None` for all.
+    fn interests(&self) -> Option<&'static [ElementType]>;
+    /// Optional rules only run if they are listed in `optional_rules`.
+    fn optional(&self) -> bool;
+    /// The ancestor path of the rule (swapped with a shared path when
+    /// several rules traverse a document together).
+    fn path_mut(&mut self) -> &mut Vec<&'e Element>;
+}
+
+/// Types of syntax tree elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ElementType {
+    Document,
+    Heading,
+    Text,
+    Formatted,
+    Paragraph,
+    Template,
+    TemplateArgument,
+    InternalReference,
+    ExternalReference,
+    ListItem,
+    List,
+    Table,
+    TableRow,
+    TableCell,
+    Comment,
+    HtmlTag,
+    Gallery,
+    Error,
+}
+
+impl<'a> From<&'a Element> for ElementType {
+    fn from(element: &'a Element) -> Self {
+        match *element {
+            Element::Document(_) => ElementType::Document,
+            Element::Heading(_) => ElementType::Heading,
+            Element::Text(_) => ElementType::Text,
+            Element::Formatted(_) => ElementType::Formatted,
+            Element::Paragraph(_) => ElementType::Paragraph,
+            Element::Template(_) => ElementType::Template,
+            Element::TemplateArgument(_) => ElementType::TemplateArgument,
+            Element::InternalReference(_) => ElementType::InternalReference,
+            Element::ExternalReference(_) => ElementType::ExternalReference,
+            Element::ListItem(_) => ElementType::ListItem,
+            Element::List(_) => ElementType::List,
+            Element::Table(_) => ElementType::Table,
+            Element::TableRow(_) => ElementType::TableRow,
+            Element::TableCell(_) => ElementType::TableCell,
+            Element::Comment(_) => ElementType::Comment,
+            Element::HtmlTag(_) => ElementType::HtmlTag,
+            Element::Gallery(_) => ElementType::Gallery,
+            Element::Error(_) => ElementType::Error,
+        }
+    }
 }
 
+/// Defines a rule with its examples. Before the examples, a rule may be
+/// marked as `optional` (only run if listed in `optional_rules`), declare
+/// the element types it is interested in, the type of the `state` it
+/// gathers while traversing and whether it implements `document_hooks`
+/// itself.
 macro_rules! rule_impl {
-    ($t:ident, $desc:expr =>
+    ($t:ident, $desc:expr => $($rest:tt)*) => {
+        rule_impl!(@options $t, $desc, false, None, false, () => $($rest)*);
+    };
+    (@options $t:ident, $desc:expr, $optional:tt, $interests:expr, $hooks:tt, $state:ty =>
+        optional => $($rest:tt)*
+    ) => {
+        rule_impl!(@options $t, $desc, true, $interests, $hooks, $state => $($rest)*);
+    };
+    (@options $t:ident, $desc:expr, $optional:tt, $interests:expr, $hooks:tt, $state:ty =>
+        interests: [$($interest:ident),*] => $($rest:tt)*
+    ) => {
+        rule_impl!(
+            @options $t, $desc, $optional, Some(&[$(ElementType::$interest),*]), $hooks, $state
+            => $($rest)*
+        );
+    };
+    (@options $t:ident, $desc:expr, $optional:tt, $interests:expr, $hooks:tt, $state:ty =>
+        state: $new_state:ty => $($rest:tt)*
+    ) => {
+        rule_impl!(@options $t, $desc, $optional, $interests, $hooks, $new_state => $($rest)*);
+    };
+    (@options $t:ident, $desc:expr, $optional:tt, $interests:expr, $hooks:tt, $state:ty =>
+        document_hooks => $($rest:tt)*
+    ) => {
+        rule_impl!(@options $t, $desc, $optional, $interests, true, $state => $($rest)*);
+    };
+    (@options $t:ident, $desc:expr, $optional:tt, $interests:expr, $hooks:tt, $state:ty =>
+        examples: $($rest:tt)*
+    ) => {
+        rule_impl!(@hooks $t, $hooks);
+        rule_impl!(@rule $t, $desc, $optional, $interests, $state => examples: $($rest)*);
+    };
+    (@hooks $t:ident, true) => {};
+    (@hooks $t:ident, false) => {
+        impl<'e, 's: 'e> DocumentHooks<'e, 's> for $t<'e> {}
+    };
+    (@rule $t:ident, $desc:expr, $optional:tt, $interests:expr, $state:ty =>
         examples: $(
             $name:ident,
             $bad:expr,
@@ -43,6 +173,7 @@ macro_rules! rule_impl {
             pub path: Vec<&'e Element>,
             pub lints: Vec<Lint>,
             pub examples: Vec<Example>,
+            pub state: $state,
         }
 
         impl<'e> Default for $t<'e> {
@@ -57,11 +188,13 @@ macro_rules! rule_impl {
                             good: $good.into(),
                             good_explanation: $good_expl.into(),
                             kind: $result.into(),
+                            code: None,
                         },
                         )*
                     ],
                     path: vec![],
                     lints: vec![],
+                    state: Default::default(),
                 }
             }
         }
@@ -116,6 +249,18 @@ macro_rules! rule_impl {
                 &self.examples
             }
 
+            fn interests(&self) -> Option<&'static [ElementType]> {
+                $interests
+            }
+
+            fn optional(&self) -> bool {
+                $optional
+            }
+
+            fn path_mut(&mut self) -> &mut Vec<&'e Element> {
+                &mut self.path
+            }
+
         }
     }
 }
@@ -135,7 +280,10 @@ impl Checkable for Element {
         rule: &mut Rule<'e, 's>,
         settings: &'s Settings,
     ) -> io::Result<&Vec<Lint>> {
+        let context = Context::new(self, settings);
+        rule.begin_document(&context)?;
         rule.run(self, settings, &mut vec![])?;
+        rule.end_document(&context)?;
         Ok(rule.lints())
     }
 }
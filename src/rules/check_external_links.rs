@@ -0,0 +1,339 @@
+use crate::preamble::*;
+use std::collections::HashSet;
+use std::mem;
+
+rule_impl!(CheckExternalLinks, "Checks external links."
+=> interests: [ExternalReference]
+=> state: Vec<&'e ExternalReference>
+=> document_hooks
+=> examples:
+    denied_link_domain,
+    "[https://bit.ly/2xyz Some article]",
+    "Link shorteners hide the actual link target from the reader.",
+    "[https://example.com/some-article Some article]",
+    "The link shows its actual target."
+    => LintKind::DeniedLinkDomain
+;
+    insecure_link,
+    "[http://example.com Example]",
+    "The link uses an unencrypted connection.",
+    "[https://example.com Example]",
+    "The link uses an encrypted connection."
+    => LintKind::InsecureLink
+;
+    link_without_caption,
+    "[https://example.com]",
+    "The link has no caption. MediaWiki shows it as a number like `[1]`, \
+     which does not tell the reader where it leads.",
+    "[https://example.com Example]",
+    "The caption describes the link target."
+    => LintKind::LinkWithoutCaption
+;
+    interwiki_link,
+    "[https://de.wikipedia.org/wiki/Primzahl Primzahl]",
+    "Wikipedia articles are linked with a full URL.",
+    "[[w:Primzahl|Primzahl]]",
+    "An interwiki link is used, which is shorter and works on every mirror \
+     of the wiki."
+    => LintKind::InterwikiLink
+;
+    duplicate_external_link,
+    "[https://example.com Example] and [https://example.com the same example]",
+    "The same page is linked twice.",
+    "[https://example.com Example] and the same example",
+    "The page is linked only once."
+    => LintKind::DuplicateExternalLink
+);
+
+/// Wiki projects which should be linked with an interwiki prefix.
+const INTERWIKI: &[(&str, &str)] = &[("wikipedia.org", "w"), ("wikibooks.org", "b")];
+
+fn link_lint(position: &Span, kind: LintKind, explanation: String, solution: String) -> Lint {
+    let (explanation_long, severity) = match kind {
+        LintKind::DeniedLinkDomain => (
+            "Links to some websites are not allowed in this project, because \
+             they are unreliable or hide their actual target.",
+            Severity::Error,
+        ),
+        LintKind::InsecureLink => (
+            "Unencrypted connections can be read and altered by third parties. \
+             Most websites support encrypted connections today.",
+            Severity::Warning,
+        ),
+        LintKind::LinkWithoutCaption => (
+            "Links without a caption are rendered as a bare URL or a number, \
+             which does not tell the reader where it leads and looks bad in \
+             print.",
+            Severity::Warning,
+        ),
+        LintKind::InterwikiLink => (
+            "Pages of other Wikimedia projects should be linked with interwiki \
+             links like `[[w:Article]]`. They are shorter and independent of the \
+             domain the wiki is served from.",
+            Severity::Info,
+        ),
+        _ => (
+            "Linking the same page several times in one article distracts the \
+             reader. Usually the first occurrence is enough.",
+            Severity::Info,
+        ),
+    };
+    Lint {
+        position: position.clone(),
+        explanation,
+        explanation_long: explanation_long.into(),
+        solution,
+        severity,
+        kind,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+// the domain of an url, without port and in lower case.
+fn domain(url: &str) -> String {
+    let rest = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url.trim_start_matches("//"),
+    };
+    rest.split(|c| c == '/' || c == '?' || c == '#')
+        .next()
+        .unwrap_or_default()
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+// is the domain equal to or a subdomain of `base`?
+fn matches_domain(domain: &str, base: &str) -> bool {
+    let base = base.trim().to_lowercase();
+    domain == base || domain.ends_with(&format!(".{}", base))
+}
+
+// decode percent-encoded characters of an url.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = input.get(index + 1..index + 3);
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(byte) if bytes[index] == b'%' => {
+                result.push(byte);
+                index += 3;
+            }
+            _ => {
+                result.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+// could this host label be the language of a wiki, like `de` or `zh-yue`?
+fn is_language(label: &str) -> bool {
+    label.len() >= 2
+        && label != "www"
+        && label.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+}
+
+// the interwiki link for an url to a page of a wikimedia project. only urls
+// naming the language of the wiki (also of its mobile version) are known.
+fn interwiki_target(url: &str, settings: &Settings) -> Option<String> {
+    let domain = domain(url);
+    let (base, prefix) = INTERWIKI
+        .iter()
+        .find(|(base, _)| matches_domain(&domain, base))?;
+    let labels: Vec<&str> = domain[..domain.len() - base.len()]
+        .split('.')
+        .filter(|l| !l.is_empty() && *l != "m")
+        .collect();
+    let language = match labels.as_slice() {
+        [language] if is_language(language) => language,
+        _ => return None,
+    };
+
+    let path = url.split("/wiki/").nth(1)?;
+    let path = path.split('?').next().unwrap_or_default();
+    let mut parts = path.splitn(2, '#');
+    let mut title = percent_decode(parts.next().unwrap_or_default()).replace('_', " ");
+    if title.trim().is_empty() {
+        return None;
+    }
+    if let Some(anchor) = parts.next().filter(|a| !a.is_empty()) {
+        title = format!("{}#{}", title, percent_decode(anchor).replace('_', " "));
+    }
+    if *language == settings.wiki_language {
+        Some(format!("{}:{}", prefix, title))
+    } else {
+        Some(format!("{}:{}:{}", prefix, language, title))
+    }
+}
+
+// does this markup only consist of plain text?
+fn is_plain(content: &[Element]) -> bool {
+    content.iter().all(|e| {
+        if let Element::Text(_) = *e {
+            true
+        } else {
+            false
+        }
+    })
+}
+
+// normalized url for comparison.
+fn normalize_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+impl<'e, 's: 'e> DocumentHooks<'e, 's> for CheckExternalLinks<'e> {
+    fn end_document(&mut self, _: &Context<'e, 's>) -> io::Result<()> {
+        let mut seen = HashSet::new();
+        for link in mem::replace(&mut self.state, vec![]) {
+            let url = normalize_url(&link.target);
+            if !seen.insert(url) {
+                self.push(link_lint(
+                    &link.position,
+                    LintKind::DuplicateExternalLink,
+                    format!("`{}` is already linked in this article.", link.target.trim()),
+                    "Remove this link or the earlier one.".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckExternalLinks<'e> {
+    path_impl!();
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        let link = if let Element::ExternalReference(ref link) = *root {
+            link
+        } else {
+            return Ok(true);
+        };
+        self.state.push(link);
+        let url = link.target.trim();
+        let domain = domain(url);
+
+        let denied = settings
+            .link_denylist
+            .iter()
+            .any(|d| matches_domain(&domain, d));
+        let allowed = settings.link_allowlist.is_empty()
+            || settings
+                .link_allowlist
+                .iter()
+                .any(|d| matches_domain(&domain, d));
+        if denied || !allowed {
+            self.push(link_lint(
+                &link.position,
+                LintKind::DeniedLinkDomain,
+                format!("Links to `{}` are not allowed!", domain),
+                "Link to the original page or another source.".into(),
+            ));
+        }
+
+        if settings.require_https && url.to_lowercase().starts_with("http://") {
+            let mut lint = link_lint(
+                &link.position,
+                LintKind::InsecureLink,
+                "This link does not use `https://`.".into(),
+                "Use `https://` instead of `http://`.".into(),
+            );
+            if is_plain(&link.caption) {
+                let caption = extract_plain_text(&link.caption);
+                let secure = format!("https://{}", &url["http://".len()..]);
+                lint.replacements.push(Replacement {
+                    position: link.position.clone(),
+                    text: if caption.trim().is_empty() {
+                        format!("[{}]", secure)
+                    } else {
+                        format!("[{} {}]", secure, caption.trim())
+                    },
+                });
+            }
+            self.push(lint);
+        }
+
+        let caption = extract_plain_text(&link.caption);
+        if caption.trim().is_empty() {
+            self.push(link_lint(
+                &link.position,
+                LintKind::LinkWithoutCaption,
+                "This link has no caption!".into(),
+                format!("Add a caption: `[{} caption]`.", url),
+            ));
+        }
+
+        if let Some(target) = interwiki_target(url, settings) {
+            let mut lint = link_lint(
+                &link.position,
+                LintKind::InterwikiLink,
+                "Pages of other wikis should be linked with interwiki links.".into(),
+                format!("Use `[[{}|...]]` instead.", target),
+            );
+            if is_plain(&link.caption) && !caption.trim().is_empty() {
+                lint.replacements.push(Replacement {
+                    position: link.position.clone(),
+                    text: format!("[[{}|{}]]", target, caption.trim()),
+                });
+            }
+            self.push(lint);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(url: &str) -> Option<String> {
+        interwiki_target(url, &Settings::default())
+    }
+
+    #[test]
+    fn interwiki_targets() {
+        assert_eq!(
+            target("https://de.wikipedia.org/wiki/Primzahl"),
+            Some("w:Primzahl".into())
+        );
+        assert_eq!(
+            target("https://en.m.wikipedia.org/wiki/Prime_number"),
+            Some("w:en:Prime number".into())
+        );
+        assert_eq!(
+            target("https://de.wikibooks.org/wiki/Mathe_f%C3%BCr_Nicht-Freaks"),
+            Some("b:Mathe für Nicht-Freaks".into())
+        );
+    }
+
+    #[test]
+    fn interwiki_query_and_anchor() {
+        assert_eq!(
+            target("https://de.wikipedia.org/wiki/Primzahl?action=history"),
+            Some("w:Primzahl".into())
+        );
+        assert_eq!(
+            target("https://de.wikipedia.org/wiki/Primzahl#Eigenschaften_von_Primzahlen"),
+            Some("w:Primzahl#Eigenschaften von Primzahlen".into())
+        );
+    }
+
+    #[test]
+    fn interwiki_without_language() {
+        assert_eq!(target("https://www.wikipedia.org/wiki/Primzahl"), None);
+        assert_eq!(target("https://wikipedia.org/wiki/Primzahl"), None);
+        assert_eq!(target("https://m.wikipedia.org/wiki/Primzahl"), None);
+        assert_eq!(target("https://de.wikipedia.org/w/index.php?title=Primzahl"), None);
+    }
+}
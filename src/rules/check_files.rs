@@ -0,0 +1,247 @@
+use crate::preamble::*;
+
+rule_impl!(CheckFiles, "Checks embedded images and other files."
+=> interests: [InternalReference]
+=> examples:
+    file_without_caption,
+    "[[Datei:Graph.svg|mini|alt=Graph of a parabola]]",
+    "The image has no caption, so the reader does not know what it shows.",
+    "[[Datei:Graph.svg|mini|alt=Graph of a parabola|The graph of f]]",
+    "The image has a caption as its last argument."
+    => LintKind::FileWithoutCaption
+;
+    file_without_alt,
+    "[[Datei:Graph.svg|mini|The graph of f]]",
+    "The image has no alternative text for readers who cannot see it.",
+    "[[Datei:Graph.svg|mini|alt=Graph of a parabola|The graph of f]]",
+    "The alternative text describes the image."
+    => LintKind::FileWithoutAlt
+;
+    illegal_file_option,
+    "[[Datei:Graph.svg|300px|links|alt=Graph of a parabola|The graph of f]]",
+    "The size and position of images are determined by the layout of the \
+     project. Setting them explicitly breaks the PDF export.",
+    "[[Datei:Graph.svg|mini|alt=Graph of a parabola|The graph of f]]",
+    "The image is shown as thumbnail in its default size and position."
+    => LintKind::IllegalFileOption
+;
+    unknown_file_namespace,
+    "[[Bild:Graph.svg|mini|alt=Graph of a parabola|The graph of f]]",
+    "`Bild:` is an old alias of the file namespace.",
+    "[[Datei:Graph.svg|mini|alt=Graph of a parabola|The graph of f]]",
+    "The file namespace is spelled `Datei:`."
+    => LintKind::UnknownFileNamespace
+;
+    unsupported_file_type,
+    "[[Datei:Animation.webm|mini|alt=Rotating cube|A rotating cube]]",
+    "Videos cannot be shown in the PDF export.",
+    "[[Datei:Cube.svg|mini|alt=A cube|A cube]]",
+    "Vector graphics are supported by all exports."
+    => LintKind::UnsupportedFileType
+);
+
+/// Options for embedded files known to MediaWiki.
+const KNOWN_OPTIONS: &[&str] = &[
+    "thumb", "thumbnail", "mini", "miniatur", "frame", "framed", "gerahmt", "frameless",
+    "rahmenlos", "border", "rand", "left", "links", "right", "rechts", "center", "zentriert",
+    "none", "ohne", "baseline", "sub", "super", "top", "text-top", "middle", "bottom",
+    "text-bottom", "hochkant", "upright", "alt", "link", "verweis", "page", "seite", "class",
+    "lang", "sprache",
+];
+
+fn file_lint(position: &Span, kind: LintKind, explanation: String, solution: String) -> Lint {
+    let (explanation_long, severity) = match kind {
+        LintKind::FileWithoutCaption | LintKind::FileWithoutAlt => (
+            "Every image needs a caption explaining its meaning and an alternative \
+             text (`alt=...`) describing it for readers who cannot see it.",
+            Severity::Warning,
+        ),
+        LintKind::IllegalFileOption => (
+            "The layout of images is determined by the project to make the \
+             appearance consistent and the export to other formats possible. \
+             Only some options for images are allowed.",
+            Severity::Warning,
+        ),
+        LintKind::UnknownFileNamespace => (
+            "MediaWiki knows several aliases for the file namespace. To make \
+             articles easier to process, only one spelling should be used.",
+            Severity::Info,
+        ),
+        LintKind::MissingFile => (
+            "This file is not in the list of available media files. It will \
+             be shown as a broken link.",
+            Severity::Error,
+        ),
+        _ => (
+            "The PDF export only supports some kinds of files. Other files \
+             will be missing in the exported document.",
+            Severity::Error,
+        ),
+    };
+    Lint {
+        position: position.clone(),
+        explanation,
+        explanation_long: explanation_long.into(),
+        solution,
+        severity,
+        kind,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+/// Lint for a file which is not in the list of media files.
+pub(crate) fn missing_file(position: &Span, file_name: &str) -> Lint {
+    file_lint(
+        position,
+        LintKind::MissingFile,
+        format!("The file `{}` does not exist!", file_name),
+        "Check the spelling of the file name or upload the file.".into(),
+    )
+}
+
+// is this option an explicit size like `300px` or `x200px`?
+fn is_size(option: &str) -> bool {
+    option.ends_with("px")
+        && option.len() > 2
+        && option[..option.len() - 2]
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == 'x')
+}
+
+// the option name of `key=value` or a keyword option.
+fn option_name(option: &str) -> String {
+    option
+        .split('=')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckFiles<'e> {
+    path_impl!();
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        let link = if let Element::InternalReference(ref link) = *root {
+            link
+        } else {
+            return Ok(true);
+        };
+
+        let target = extract_plain_text(&link.target);
+        let target = target.trim();
+        // links to the file description page do not embed the file.
+        if target.starts_with(':') {
+            return Ok(true);
+        }
+        let (namespace, file_name) = match target.find(':') {
+            Some(index) => (target[..index].trim(), target[index + 1..].trim()),
+            None => return Ok(true),
+        };
+        let embeds_file = settings
+            .file_namespace_aliases
+            .iter()
+            .any(|n| n.to_lowercase() == namespace.to_lowercase());
+        if !embeds_file {
+            return Ok(true);
+        }
+
+        if !settings.file_namespaces.iter().any(|n| n == namespace) {
+            let allowed = settings
+                .file_namespaces
+                .first()
+                .map(|n| n.as_str())
+                .unwrap_or("Datei");
+            self.push(file_lint(
+                &link.position,
+                LintKind::UnknownFileNamespace,
+                format!("`{}:` is not the usual spelling of the file namespace.", namespace),
+                format!("Use `{}:` instead.", allowed),
+            ));
+        }
+
+        let extension = file_name
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if !settings.file_types.contains(&extension) {
+            self.push(file_lint(
+                &link.position,
+                LintKind::UnsupportedFileType,
+                format!("Files of type `{}` are not supported!", extension),
+                format!(
+                    "Use a file of one of the following types: {}",
+                    settings.file_types.join(", ")
+                ),
+            ));
+        }
+
+        if let Some(ref media_files) = settings.media_files {
+            if !media_files.contains(&file_name.replace('_', " ")) {
+                self.push(missing_file(&link.position, file_name));
+            }
+        }
+
+        let mut has_caption = false;
+        let mut has_alt = false;
+        let parts = link.options.iter().chain(Some(&link.caption));
+        for part in parts {
+            let text = extract_plain_text(part);
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let name = option_name(text);
+            if is_size(text) {
+                if !settings.file_allow_size {
+                    self.push(file_lint(
+                        &link.position,
+                        LintKind::IllegalFileOption,
+                        format!("The image size `{}` should not be set explicitly.", text),
+                        "Remove the size option.".into(),
+                    ));
+                }
+            } else if KNOWN_OPTIONS.contains(&name.as_str()) {
+                has_alt |= name == "alt";
+                if !settings.file_options.contains(&name) {
+                    self.push(file_lint(
+                        &link.position,
+                        LintKind::IllegalFileOption,
+                        format!("The image option `{}` is not allowed.", name),
+                        format!(
+                            "Only use the following options: {}",
+                            settings.file_options.join(", ")
+                        ),
+                    ));
+                }
+            } else {
+                has_caption = true;
+            }
+        }
+
+        if !has_caption {
+            self.push(file_lint(
+                &link.position,
+                LintKind::FileWithoutCaption,
+                "This image has no caption!".into(),
+                "Add a caption as last argument: `[[Datei:...|...|caption]]`.".into(),
+            ));
+        }
+        if !has_alt {
+            self.push(file_lint(
+                &link.position,
+                LintKind::FileWithoutAlt,
+                "This image has no alternative text!".into(),
+                "Describe the image with `alt=...`.".into(),
+            ));
+        }
+        Ok(true)
+    }
+}
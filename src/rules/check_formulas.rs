@@ -3,6 +3,7 @@ use crate::preamble::*;
 use mwparser_utils::TexResult;
 
 rule_impl!(CheckFormulas, "Verify math formulas."
+=> interests: [Formatted]
 => examples:
     math_syntax_error,
     "<math>\\frac{1}{2</math>",
@@ -72,6 +73,8 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckFormulas<'e> {
                             .into(),
                         severity: Severity::Warning,
                         kind: LintKind::DeprecatedRightarrow,
+                        replacements: vec![],
+                        code: None,
                     };
                     self.push(arrow_lint);
                 }
@@ -90,6 +93,8 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckFormulas<'e> {
                         solution: "Replace \\Leftrightarrow by \\iff.".into(),
                         severity: Severity::Warning,
                         kind: LintKind::DeprecatedLeftrightarrow,
+                        replacements: vec![],
+                        code: None,
                     };
                     self.push(arrow_lint);
                 }
@@ -144,6 +149,8 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckFormulas<'e> {
                             .into(),
                         severity: Severity::Error,
                         kind: error.1,
+                        replacements: vec![],
+                        code: None,
                     };
                     self.push(err_lint);
                 }
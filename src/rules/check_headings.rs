@@ -1,6 +1,10 @@
 use crate::preamble::*;
+use std::mem;
 
 rule_impl!(CheckHeadings, "Checks for erroneous headings."
+=> interests: [Heading]
+=> state: Vec<&'e Heading>
+=> document_hooks
 => examples:
     deep_heading,
     "===== deep heading =====\n",
@@ -21,6 +25,20 @@ rule_impl!(CheckHeadings, "Checks for erroneous headings."
     "The heading hierarchy is consistent. The lower level is exactly one level
      deeper than its parent heading."
     => LintKind::InconsistentHeadingHierarchy
+;
+    duplicate_heading,
+    "== Example ==\n\
+     Text.\n\
+     == Example ==\n\
+     More text.\n",
+    "Both headings have the same caption, so a link to `#Example` always \
+     leads to the first one.",
+    "== First example ==\n\
+     Text.\n\
+     == Second example ==\n\
+     More text.\n",
+    "Every heading has its own caption."
+    => LintKind::DuplicateHeading
 );
 
 fn max_depth_lint(settings: &Settings, position: &Span) -> Lint {
@@ -39,6 +57,8 @@ fn max_depth_lint(settings: &Settings, position: &Span) -> Lint {
             .into(),
         severity: Severity::Warning,
         kind: LintKind::MaxHeadingDepthViolation,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -57,6 +77,41 @@ fn inconsistent_hierarchy_lint(position: &Span, diff: usize) -> Lint {
         solution: format!("Reduce depth of this heading by {}.", diff),
         severity: Severity::Warning,
         kind: LintKind::InconsistentHeadingHierarchy,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn duplicate_heading(position: &Span, caption: &str) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!("There is already a heading \"{}\" in this article!", caption),
+        explanation_long: "Links to a section use the heading caption as anchor. If two \
+                           headings have the same caption, only the first one can be \
+                           linked. Distinct headings also make the table of contents \
+                           easier to read."
+            .into(),
+        solution: "Choose a caption which describes the content of this section.".into(),
+        severity: Severity::Warning,
+        kind: LintKind::DuplicateHeading,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+impl<'e, 's: 'e> DocumentHooks<'e, 's> for CheckHeadings<'e> {
+    fn end_document(&mut self, _: &Context<'e, 's>) -> io::Result<()> {
+        let mut captions = vec![];
+        for heading in mem::replace(&mut self.state, vec![]) {
+            let caption = extract_plain_text(&heading.caption);
+            let caption = caption.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if captions.contains(&caption) {
+                self.push(duplicate_heading(&heading.position, &caption));
+            } else {
+                captions.push(caption);
+            }
+        }
+        Ok(())
     }
 }
 
@@ -70,6 +125,8 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckHeadings<'e> {
         _: &mut io::Write,
     ) -> io::Result<bool> {
         if let Element::Heading(ref heading) = *root {
+            self.state.push(heading);
+
             // is heading too deep?
             if heading.depth > settings.max_heading_depth {
                 self.push(max_depth_lint(settings, &heading.position));
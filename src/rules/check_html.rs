@@ -1,7 +1,12 @@
 use crate::lint::{Example, Lint};
 use crate::preamble::*;
+use std::collections::HashSet;
+use std::mem;
 
 rule_impl!(CheckHtml, "Hints for text elements."
+=> interests: [HtmlTag]
+=> state: HtmlTags<'e>
+=> document_hooks
 => examples:
     illegal_span,
     "<big>big text</big>",
@@ -9,8 +14,300 @@ rule_impl!(CheckHtml, "Hints for text elements."
     "'''fat text'''",
     "Bold text highlights just as well."
     => LintKind::IllegalHtml
+;
+    illegal_style_attribute,
+    "<dfn style=\"color: red\">term</dfn>",
+    "The `style` attribute allows arbitrary CSS formatting, which is not \
+     portable.",
+    "<dfn>term</dfn>",
+    "The definition is marked without custom formatting."
+    => LintKind::IllegalHtmlAttribute
+;
+    unknown_ref_group,
+    "Fact.<ref group=\"sources\">Some book</ref>",
+    "The reference group `sources` is not used in this project.",
+    "Fact.<ref group=\"note\">Some book</ref>\n\
+     <references group=\"note\" />",
+    "The reference uses one of the allowed groups."
+    => LintKind::UnknownRefGroup
+;
+    empty_ref,
+    "Fact.<ref name=\"book\" />",
+    "The reference has no content and there is no previous reference named \
+     `book` it could refer to.",
+    "Fact.<ref name=\"book\">Some book</ref> Another fact.<ref name=\"book\" />\n\
+     <references />",
+    "The second reference reuses the content of the first reference named \
+     `book`."
+    => LintKind::EmptyRef
+;
+    missing_references,
+    "Fact.<ref>Some book</ref>",
+    "There is no `<references />` tag, so the content of the reference is \
+     not shown anywhere.",
+    "Fact.<ref>Some book</ref>\n\
+     <references />",
+    "The references are listed at the end of the article."
+    => LintKind::MissingReferences
+;
+    unused_ref_name,
+    "Fact.<ref name=\"book\">Some book</ref>\n\
+     <references />",
+    "The reference is named `book`, but the name is never used.",
+    "Fact.<ref>Some book</ref>\n\
+     <references />",
+    "The reference has no name, as it is used only once."
+    => LintKind::UnusedRefName
+;
+    unclosed_section,
+    "<section begin=\"proof\" />The proof.",
+    "The section `proof` is never closed.",
+    "<section begin=\"proof\" />The proof.<section end=\"proof\" />",
+    "The section `proof` is closed after its content."
+    => LintKind::UnclosedSection
+;
+    mismatched_section,
+    "<section begin=\"a\" /><section begin=\"b\" />Text.\
+     <section end=\"a\" /><section end=\"b\" />",
+    "The section `a` is closed while the section `b` inside it is still open.",
+    "<section begin=\"a\" /><section begin=\"b\" />Text.\
+     <section end=\"b\" /><section end=\"a\" />",
+    "The inner section `b` is closed before the outer section `a`."
+    => LintKind::MismatchedSection
 );
 
+/// Tags checked for the whole document, in source order.
+#[derive(Default)]
+pub struct HtmlTags<'e> {
+    refs: Vec<&'e HtmlTag>,
+    references: Vec<&'e HtmlTag>,
+    sections: Vec<&'e HtmlTag>,
+}
+
+fn illegal_attribute(position: &Span, tag: &str, attribute: &str, allowed: &[String]) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!(
+            "The attribute \"{}\" is not allowed for \"{}\".",
+            attribute, tag
+        ),
+        explanation_long: format!(
+            "Attributes like `style` allow arbitrary formatting which is not \
+             portable and impairs consistency. \"{}\" only allows the following \
+             attributes: {}",
+            tag,
+            allowed
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        solution: "Remove the attribute.".into(),
+        severity: Severity::Error,
+        kind: LintKind::IllegalHtmlAttribute,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn unknown_ref_group(position: &Span, group: &str, allowed: &[String]) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!("The reference group \"{}\" is unknown.", group),
+        explanation_long: "References can be put in groups which are listed separately. \
+                           Only a fixed set of groups is used in this project, so they \
+                           are rendered consistently."
+            .into(),
+        solution: format!(
+            "Use one of the following groups: {}",
+            allowed
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        severity: Severity::Warning,
+        kind: LintKind::UnknownRefGroup,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn empty_ref(position: &Span) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: "This reference is empty!".into(),
+        explanation_long: "A reference without content is only useful to reuse a \
+                           reference which was named and given before, as in \
+                           `<ref name=\"x\">content</ref> ... <ref name=\"x\" />`."
+            .into(),
+        solution: "Add the content of the reference or use the name of a previous \
+                   reference."
+            .into(),
+        severity: Severity::Error,
+        kind: LintKind::EmptyRef,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn missing_references(position: &Span, group: Option<&str>) -> Lint {
+    let tag = match group {
+        Some(group) => format!("<references group=\"{}\" />", group),
+        None => "<references />".into(),
+    };
+    Lint {
+        position: position.clone(),
+        explanation: format!("There is no `{}` for this reference!", tag),
+        explanation_long: "The content of references is shown where the matching \
+                           `<references />` tag is placed. Without it, MediaWiki shows \
+                           an error message instead."
+            .into(),
+        solution: format!("Add `{}` at the end of the article.", tag),
+        severity: Severity::Error,
+        kind: LintKind::MissingReferences,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn unused_ref_name(position: &Span, name: &str) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!("The reference name \"{}\" is never used.", name),
+        explanation_long: "Names of references are only needed to use the same \
+                           reference several times, as in \
+                           `<ref name=\"x\">content</ref> ... <ref name=\"x\" />`."
+            .into(),
+        solution: "Remove the name or reuse the reference.".into(),
+        severity: Severity::Info,
+        kind: LintKind::UnusedRefName,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn unclosed_section(position: &Span, name: &str) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!("The section \"{}\" is never closed.", name),
+        explanation_long: "Sections marked with `<section begin=\"...\" />` can be included \
+                           in other articles. A section reaches until the matching \
+                           `<section end=\"...\" />`."
+            .into(),
+        solution: format!(
+            "Add `<section end=\"{}\" />` after the content of the section.",
+            name
+        ),
+        severity: Severity::Error,
+        kind: LintKind::UnclosedSection,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn mismatched_section(position: &Span, message: String) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: message,
+        explanation_long: "Sections marked with `<section begin=\"...\" />` and \
+                           `<section end=\"...\" />` should be properly nested, such that \
+                           an inner section is closed before its outer section."
+            .into(),
+        solution: "Check the names and the order of the section markers.".into(),
+        severity: Severity::Error,
+        kind: LintKind::MismatchedSection,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+impl<'e> CheckHtml<'e> {
+    fn check_refs(&mut self, tags: &[&HtmlTag], references: &[&HtmlTag]) {
+        let mut named = HashSet::new();
+        let mut reused = HashSet::new();
+        for tag in tags {
+            let name = tag_attribute(tag, "name");
+            if extract_plain_text(&tag.content).trim().is_empty() {
+                match name {
+                    Some(name) if named.contains(name) => {
+                        reused.insert(name);
+                    }
+                    _ => self.push(empty_ref(&tag.position)),
+                }
+            } else if let Some(name) = name {
+                if !named.insert(name) {
+                    reused.insert(name);
+                }
+            }
+        }
+
+        for tag in tags {
+            if let Some(name) = tag_attribute(tag, "name") {
+                let has_content = !extract_plain_text(&tag.content).trim().is_empty();
+                if has_content && !reused.contains(name) {
+                    self.push(unused_ref_name(&tag.position, name));
+                }
+            }
+        }
+
+        // one lint per reference group without a list.
+        let mut missing = vec![];
+        for tag in tags {
+            let group = tag_attribute(tag, "group");
+            let listed = references
+                .iter()
+                .any(|r| tag_attribute(r, "group") == group);
+            if !listed && !missing.contains(&group) {
+                missing.push(group);
+                self.push(missing_references(&tag.position, group));
+            }
+        }
+    }
+
+    fn check_sections(&mut self, tags: &[&HtmlTag]) {
+        let mut open: Vec<(&str, &Span)> = vec![];
+        for tag in tags {
+            if let Some(name) = tag_attribute(tag, "begin") {
+                open.push((name, &tag.position));
+            }
+            if let Some(name) = tag_attribute(tag, "end") {
+                match open.iter().rposition(|(n, _)| *n == name) {
+                    Some(index) if index == open.len() - 1 => {
+                        open.pop();
+                    }
+                    Some(index) => {
+                        let message = format!(
+                            "The section \"{}\" is closed before the section \"{}\" inside it.",
+                            name,
+                            open[open.len() - 1].0
+                        );
+                        self.push(mismatched_section(&tag.position, message));
+                        open.remove(index);
+                    }
+                    None => {
+                        let message =
+                            format!("The section \"{}\" is closed but never opened.", name);
+                        self.push(mismatched_section(&tag.position, message));
+                    }
+                }
+            }
+        }
+        for (name, position) in open {
+            self.push(unclosed_section(position, name));
+        }
+    }
+}
+
+impl<'e, 's: 'e> DocumentHooks<'e, 's> for CheckHtml<'e> {
+    fn end_document(&mut self, _: &Context<'e, 's>) -> io::Result<()> {
+        let tags = mem::replace(&mut self.state, HtmlTags::default());
+        self.check_refs(&tags.refs, &tags.references);
+        self.check_sections(&tags.sections);
+        Ok(())
+    }
+}
+
 impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckHtml<'e> {
     path_impl!();
 
@@ -21,10 +318,15 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckHtml<'e> {
         _: &mut io::Write,
     ) -> io::Result<bool> {
         if let Element::HtmlTag(ref html) = *root {
-            if !settings
-                .html_whitelist
-                .contains(&html.name.trim().to_lowercase())
-            {
+            let tag_name = html.name.trim().to_lowercase();
+            if let Some(allowed) = settings.html_whitelist.get(&tag_name) {
+                for attr in &html.attributes {
+                    let key = attr.key.trim().to_lowercase();
+                    if !allowed.contains(&key) {
+                        self.push(illegal_attribute(&attr.position, &tag_name, &key, allowed));
+                    }
+                }
+            } else {
                 let html_lint = Lint {
                     position: html.position.clone(),
                     explanation: format!("\"{}\" is not allowed for this project.", &html.name),
@@ -35,9 +337,30 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckHtml<'e> {
                     solution: "Use MediaWiki markup or allowed templtes.".into(),
                     severity: Severity::Error,
                     kind: LintKind::IllegalHtml,
+                    replacements: vec![],
+                    code: None,
                 };
                 self.push(html_lint);
             }
+
+            match tag_name.as_str() {
+                "ref" => self.state.refs.push(html),
+                "references" => self.state.references.push(html),
+                "section" => self.state.sections.push(html),
+                _ => (),
+            }
+
+            if tag_name == "ref" {
+                if let Some(group) = tag_attribute(html, "group") {
+                    if !settings.ref_groups.iter().any(|g| g == group) {
+                        self.push(unknown_ref_group(
+                            &html.position,
+                            group,
+                            &settings.ref_groups,
+                        ));
+                    }
+                }
+            }
         }
         Ok(true)
     }
@@ -1,6 +1,7 @@
 use crate::preamble::*;
 
 rule_impl!(CheckLists, "Checks for malformed lists"
+=> interests: [List]
 => examples:
     definition_term_without_def,
     "; term 1\n\
@@ -50,7 +51,7 @@ rule_impl!(CheckLists, "Checks for malformed lists"
     "`defintion 2` follows the definition term `term 2`."
     => LintKind::DefinitionWithoutTerm
 ;
-    list_one_item_split_lists,
+    split_list,
     "* item 1\n\
      \n\
      * item 2",
@@ -61,7 +62,21 @@ rule_impl!(CheckLists, "Checks for malformed lists"
      * item 2",
     "We only have list with two items since there is no empty line betweens \
      the lines."
-    => LintKind::ListOneElement
+    => LintKind::SplitList
+;
+    split_list_many,
+    "# step 1\n\
+     \n\
+     # step 2\n\
+     \n\
+     # step 3",
+    "The empty lines split the enumeration into three lists. Every list \
+     starts counting at one again.",
+    "# step 1\n\
+     # step 2\n\
+     # step 3",
+    "The enumeration is one list with three items."
+    => LintKind::SplitList
 ;
     list_one_item_important_paragraph,
     "Some paragraph.\n\
@@ -128,6 +143,8 @@ fn term_without_def(position: &Span) -> Lint {
             .into(),
         severity: Severity::Warning,
         kind: LintKind::DefinitionTermWithoutDef,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -147,6 +164,8 @@ fn def_without_term(position: &Span) -> Lint {
             .into(),
         severity: Severity::Warning,
         kind: LintKind::DefinitionWithoutTerm,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -164,6 +183,29 @@ fn list_one_element(position: &Span) -> Lint {
             .into(),
         severity: Severity::Info,
         kind: LintKind::ListOneElement,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn split_list(position: &Span, count: usize, replacements: Vec<Replacement>) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!(
+            "These {} lists are separated only by empty lines. \
+             Did you mean to write one list?",
+            count
+        ),
+        explanation_long: "An empty line ends a list in MediaWiki. Items separated by \
+                           empty lines thus form several lists with one or few items \
+                           instead of a single list. Ordered lists will start counting \
+                           at one again after each empty line."
+            .into(),
+        solution: "Delete the empty lines between the list items.".into(),
+        severity: Severity::Warning,
+        kind: LintKind::SplitList,
+        replacements,
+        code: None,
     }
 }
 
@@ -181,6 +223,8 @@ fn list_mixed_type(position: &Span) -> Lint {
             .into(),
         severity: Severity::Error,
         kind: LintKind::ListMixedType,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -193,15 +237,102 @@ fn term_to_def(kind: &ListItemKind) -> ListItemKind {
     }
 }
 
+// semantic kind of a list, determined by its first item.
+fn list_kind(list: &List) -> Option<ListItemKind> {
+    list.content.iter().find_map(|item| {
+        if let Element::ListItem(ref item) = *item {
+            Some(term_to_def(&item.kind))
+        } else {
+            None
+        }
+    })
+}
+
+// is this element only an empty line between two lists?
+fn is_blank(element: &Element) -> bool {
+    match *element {
+        Element::Paragraph(ref par) => extract_plain_text(&par.content).trim().is_empty(),
+        Element::Text(ref text) => text.text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+// source changes joining a group of lists. the gap between two lists only
+// holds blank lines and is replaced as a whole. a list starts at the
+// beginning of a line, so the newline ending the previous list is kept if
+// the span of that list does not include it.
+fn join_lists(group: &[&List]) -> Vec<Replacement> {
+    group
+        .windows(2)
+        .map(|pair| {
+            let (prev, next) = (&pair[0].position, &pair[1].position);
+            Replacement {
+                position: Span {
+                    start: prev.end.clone(),
+                    end: next.start.clone(),
+                },
+                text: if prev.end.col == 1 { "" } else { "\n" }.into(),
+            }
+        })
+        .collect()
+}
+
+impl<'e> CheckLists<'e> {
+    fn check_group(&mut self, group: &[&List]) {
+        match group.len() {
+            0 => (),
+            1 => {
+                if group[0].content.len() == 1 {
+                    self.push(list_one_element(&group[0].position));
+                }
+            }
+            count => {
+                let position = Span {
+                    start: group[0].position.start.clone(),
+                    end: group[count - 1].position.end.clone(),
+                };
+                self.push(split_list(&position, count, join_lists(group)));
+            }
+        }
+    }
+}
+
 impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckLists<'e> {
     path_impl!();
 
-    fn work(&mut self, root: &'e Element, _: &Settings, _: &mut io::Write) -> io::Result<bool> {
-        if let Element::List(ref list) = *root {
-            if list.content.len() == 1 {
-                self.push(list_one_element(&list.position));
+    fn work_vec(
+        &mut self,
+        root: &'e [Element],
+        _: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        // find consecutive lists of the same kind only separated by blank lines.
+        let mut group: Vec<&List> = vec![];
+
+        for element in root {
+            if let Element::List(ref list) = *element {
+                let continues = group
+                    .last()
+                    .map(|prev| list_kind(prev) == list_kind(list))
+                    .unwrap_or(false);
+                if !continues {
+                    self.check_group(&group);
+                    group.clear();
+                }
+                group.push(list);
+            } else if is_blank(element) && !group.is_empty() {
+                continue;
+            } else {
+                self.check_group(&group);
+                group.clear();
             }
+        }
+        self.check_group(&group);
+        Ok(true)
+    }
 
+    fn work(&mut self, root: &'e Element, _: &Settings, _: &mut io::Write) -> io::Result<bool> {
+        if let Element::List(ref list) = *root {
             let mut previous_kind = None;
             let mut previous_item: Option<&ListItem> = None;
             for (index, item) in list.content.iter().enumerate() {
@@ -242,3 +373,31 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckLists<'e> {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(source: &str) -> String {
+        let settings = Settings::default();
+        let tree = parse(source).unwrap();
+        let mut rule = CheckLists::default();
+        let lints = tree.check(&mut rule, &settings).unwrap();
+        let lint = lints.iter().find(|l| l.kind == LintKind::SplitList).unwrap();
+        apply_replacements(source, &lint.replacements)
+    }
+
+    #[test]
+    fn split_list_fix() {
+        assert_eq!(fixed("* item 1\n\n* item 2"), "* item 1\n* item 2");
+        assert_eq!(fixed("* item 1\n\n\n* item 2"), "* item 1\n* item 2");
+    }
+
+    #[test]
+    fn split_list_many_fix() {
+        assert_eq!(
+            fixed("# step 1\n\n# step 2\n\n# step 3"),
+            "# step 1\n# step 2\n# step 3"
+        );
+    }
+}
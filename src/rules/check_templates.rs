@@ -1,9 +1,11 @@
 use crate::preamble::*;
 #[cfg(feature = "web")]
-use mfnf_template_spec::markdown;
-use mfnf_template_spec::{is_plain_text, parse_template, spec_meta, spec_of};
+use crate::template_spec::markdown;
+use crate::template_spec::{spec_of, ArgumentSpec, Priority, TemplateSpec};
+use mfnf_template_spec::is_plain_text;
 
 rule_impl!(CheckTemplates, "Checks for the correct use of templates."
+=> interests: [Template]
 => examples:
     unknown_template,
     "{{unknown_template|arg1}}",
@@ -12,6 +14,14 @@ rule_impl!(CheckTemplates, "Checks for the correct use of templates."
     "{{Formel|<math>1+1=2</math>}}",
     "The `{{Formel|...}}` template exists and is used properly."
     => LintKind::TemplateNotAllowed
+;
+    misspelled_template,
+    "{{Formle|<math>x^2</math>}}",
+    "There is no template `Formle`, but a template `Formel` with a very \
+     similar name.",
+    "{{Formel|<math>x^2</math>}}",
+    "The name of the template is spelled correctly."
+    => LintKind::TemplateNotAllowed
 ;
     formatted_template_name,
     "{{template-{{foo}}|arg}}",
@@ -60,6 +70,49 @@ rule_impl!(CheckTemplates, "Checks for the correct use of templates."
     "{{Formel|<math>x^2</math>}}",
     "The invalid parameter was deleted."
     => LintKind::IllegalArgument
+;
+    duplicate_argument,
+    "{{Formel|<math>x^2</math>|1=<math>y^2</math>}}",
+    "The formula is given twice, once as unnamed and once as named argument \
+     `1`. MediaWiki silently ignores the first formula.",
+    "{{Formel|<math>y^2</math>}}",
+    "The formula is given only once."
+    => LintKind::DuplicateTemplateArgument
+;
+    duplicate_argument_alias,
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe \n\
+      |teilaufgabe1=Was ist der Sinn des Lebens? \n\
+      |teilaufgabe1-solution=42 \n\
+      |teilaufgabe1-lösung=23 \n\
+    }}",
+    "`teilaufgabe1-solution` and `teilaufgabe1-lösung` are two names of the \
+     same argument, so the solution is given twice.",
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe \n\
+      |teilaufgabe1=Was ist der Sinn des Lebens? \n\
+      |teilaufgabe1-lösung=42 \n\
+    }}",
+    "The solution is given only once."
+    => LintKind::DuplicateTemplateArgument
+;
+    nested_template,
+    "{{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Remark \
+     {{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Another remark}}}}",
+    "A remark box inside a remark box is confusing and can not be exported.",
+    "{{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Remark}}\n\
+     {{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Another remark}}",
+    "The two remarks are given one after another."
+    => LintKind::IllegalTemplateNesting
+;
+    deeply_nested_template,
+    "{{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Remark \
+     {{:Mathe für Nicht-Freaks: Vorlage:Warnung|Warning \
+     {{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Another remark}}}}}}",
+    "The remark box is not directly inside another remark box, but still \
+     inside the outer one.",
+    "{{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Remark}}\n\
+     {{:Mathe für Nicht-Freaks: Vorlage:Hinweis|Another remark}}",
+    "The two remarks are given one after another."
+    => LintKind::IllegalTemplateNesting
 ;
     illegal_formula_content,
     "{{Formel|<math>x^2</math> and <math>b^2</math>}}",
@@ -76,7 +129,32 @@ rule_impl!(CheckTemplates, "Checks for the correct use of templates."
     => LintKind::IllegalSectionName
 );
 
-fn template_not_allowed(position: &Span, name: &str) -> Lint {
+fn template_not_allowed(
+    position: &Span,
+    name: &str,
+    suggestions: &[&str],
+    replacements: Vec<Replacement>,
+) -> Lint {
+    let solution = if suggestions.is_empty() {
+        format!(
+            "Use another template. Maybe this is just a spelling \
+             mistake? You can also contact the main authors so that \
+             they add the template `{}` to the project \
+             specification.",
+            name
+        )
+    } else {
+        format!(
+            "Did you mean {}? Otherwise, you can contact the main authors \
+             so that they add the template `{}` to the project specification.",
+            suggestions
+                .iter()
+                .map(|s| format!("`{{{{{}}}}}`", s))
+                .collect::<Vec<String>>()
+                .join(" or "),
+            name
+        )
+    };
     Lint {
         position: position.clone(),
         explanation: format!("The template `{}` is not allowed / specified!", name),
@@ -87,15 +165,11 @@ fn template_not_allowed(position: &Span, name: &str) -> Lint {
                            For a list of allowed templates and examples see \
                            [Formatierung von Kapiteln](https://de.wikibooks.org/wiki/Mathe_f%C3%BCr_Nicht-Freaks:_Formatierung_von_Kapiteln)."
             .into(),
-        solution: format!(
-            "Use another template. Maybe this is just a spelling \
-             mistake? You can also contact the main authors so that \
-             they add the template `{}` to the project \
-             specification.",
-            name
-        ),
+        solution,
         severity: Severity::Error,
         kind: LintKind::TemplateNotAllowed,
+        replacements,
+        code: None,
     }
 }
 
@@ -111,6 +185,8 @@ fn invalid_template_name(position: &Span) -> Lint {
         solution: "Use better template names.".into(),
         severity: Severity::Error,
         kind: LintKind::InvalidTemplateName,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -134,6 +210,8 @@ fn deprecated_name(
         solution: format!("Use `{}` instead of `{}`.", better, used),
         severity: Severity::Info,
         kind,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -156,6 +234,8 @@ fn missing_argument(position: &Span, name: &str) -> Lint {
         ),
         severity: Severity::Error,
         kind: LintKind::MissingTemplateArgument,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -182,6 +262,8 @@ fn illegal_content(
             .into(),
         severity: Severity::Error,
         kind: LintKind::IllegalArgumentContent,
+        replacements: vec![],
+        code: None,
     }
 }
 
@@ -190,7 +272,21 @@ fn illegal_argument(
     argument_name: &str,
     template_name: &str,
     allowed: &[&str],
+    suggestions: &[&str],
+    replacements: Vec<Replacement>,
 ) -> Lint {
+    let solution = if suggestions.is_empty() {
+        "Only use the allowed template arguments.".to_string()
+    } else {
+        format!(
+            "Did you mean {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<String>>()
+                .join(" or ")
+        )
+    };
     Lint {
         position: position.clone(),
         explanation: format!(
@@ -207,12 +303,164 @@ fn illegal_argument(
                 .collect::<Vec<String>>()
                 .join(", ")
         ),
-        solution: "Only use the allowed template arguments.".into(),
+        solution,
         severity: Severity::Warning,
         kind: LintKind::IllegalArgument,
+        replacements,
+        code: None,
+    }
+}
+
+// remove duplicate names, keeping the order.
+fn unique<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Vec<&'a str> {
+    let mut result = vec![];
+    for name in names {
+        if !result.contains(&name) {
+            result.push(name);
+        }
+    }
+    result
+}
+
+// the position after some text starting at `start`.
+fn advance(start: &Position, text: &str) -> Position {
+    let mut position = start.clone();
+    position.offset += text.len();
+    for c in text.chars() {
+        if c == '\n' {
+            position.line += 1;
+            position.col = 1;
+        } else {
+            position.col += 1;
+        }
+    }
+    position
+}
+
+// position of the name of a template, without surrounding whitespace.
+fn name_position(template: &Template) -> Span {
+    let (first, last) = match (template.name.first(), template.name.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return template.position.clone(),
+    };
+    let start = match *first {
+        Element::Text(ref text) => {
+            let trimmed = text.text.trim_start();
+            advance(&text.position.start, &text.text[..text.text.len() - trimmed.len()])
+        }
+        _ => first.get_position().start.clone(),
+    };
+    let end = match *last {
+        Element::Text(ref text) => advance(&text.position.start, text.text.trim_end()),
+        _ => last.get_position().end.clone(),
+    };
+    Span { start, end }
+}
+
+// replace the name of a template argument, keeping its value. the span of
+// an argument starts at its `|`, which is kept as well.
+fn rename_argument(arg: &TemplateArgument, name: &str) -> Replacement {
+    let end = match arg.value.first() {
+        Some(value) => value.get_position().start.clone(),
+        None => arg.position.end.clone(),
+    };
+    Replacement {
+        position: Span {
+            start: arg.position.start.clone(),
+            end,
+        },
+        text: format!("|{}=", name),
     }
 }
 
+fn duplicate_argument(position: &Span, name: &str, others: &[&Span]) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation: format!("The argument `{}` is given more than once!", name),
+        explanation_long: format!(
+            "If a template argument is given several times, MediaWiki silently \
+             uses the last value and ignores all others. Note that unnamed \
+             arguments are numbered, so `{{{{name|value}}}}` is the same as \
+             `{{{{name|1=value}}}}`, and that deprecated and current names of an \
+             argument refer to the same argument. The argument `{}` is also \
+             given at {}.",
+            name,
+            others
+                .iter()
+                .map(|p| format!("{}:{}", p.start.line, p.start.col))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        solution: "Remove all but one of the occurrences.".into(),
+        severity: Severity::Error,
+        kind: LintKind::DuplicateTemplateArgument,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+fn illegal_nesting(position: &Span, explanation: String, solution: String) -> Lint {
+    Lint {
+        position: position.clone(),
+        explanation,
+        explanation_long: "Some templates only make sense in certain contexts. For example, \
+                           a proof step belongs into a proof, a formula can not contain \
+                           exercises and boxes should not be put into boxes of the same \
+                           kind. The allowed nesting is defined in the template \
+                           specification."
+            .into(),
+        solution,
+        severity: Severity::Error,
+        kind: LintKind::IllegalTemplateNesting,
+        replacements: vec![],
+        code: None,
+    }
+}
+
+// specifications of the templates containing an element, innermost first.
+fn ancestor_specs<'a, 'p>(
+    path: &[&Element],
+    settings: &'a Settings<'p>,
+) -> Vec<&'a TemplateSpec<'p>> {
+    path.iter()
+        .rev()
+        .filter_map(|e| {
+            if let Element::Template(ref template) = **e {
+                let name = extract_plain_text(&template.name).trim().to_lowercase();
+                spec_of(&settings.template_spec, &name)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// group the arguments of a template by the argument they refer to.
+fn argument_groups<'t>(
+    template: &'t Template,
+    spec: Option<&TemplateSpec>,
+) -> Vec<(String, Vec<&'t TemplateArgument>)> {
+    let mut groups: Vec<(String, Vec<&TemplateArgument>)> = vec![];
+    let mut position = 0;
+    for argument in &template.content {
+        if let Element::TemplateArgument(ref arg) = *argument {
+            let mut name = arg.name.trim().to_lowercase();
+            if name.is_empty() {
+                position += 1;
+                name = position.to_string();
+            }
+            if let Some(arg_spec) = spec.and_then(|s| s.argument(&name)) {
+                name = arg_spec.default_name().trim().to_lowercase();
+            }
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, args)) => args.push(arg),
+                None => groups.push((name, vec![arg])),
+            }
+        }
+    }
+    groups
+}
+
 fn illegal_section(position: &Span, message: &str) -> Lint {
     Lint {
         position: position.clone(),
@@ -224,13 +472,20 @@ fn illegal_section(position: &Span, message: &str) -> Lint {
         solution: "Only use the allowed template arguments.".into(),
         severity: Severity::Error,
         kind: LintKind::IllegalSectionName,
+        replacements: vec![],
+        code: None,
     }
 }
 
 impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
     path_impl!();
 
-    fn work(&mut self, root: &'e Element, _: &Settings, _: &mut io::Write) -> io::Result<bool> {
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
         if let Element::Template(ref template) = *root {
             if is_plain_text(&template.name).is_err() {
                 self.push(invalid_template_name(&template.position));
@@ -273,41 +528,117 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                 return Ok(true);
             }
 
-            if let Some(template_spec) = spec_of(&template_name) {
+            let spec = spec_of(&settings.template_spec, &template_name);
+
+            for (name, args) in argument_groups(template, spec) {
+                if args.len() < 2 {
+                    continue;
+                }
+                for arg in &args {
+                    let others: Vec<&Span> = args
+                        .iter()
+                        .filter(|other| other.position != arg.position)
+                        .map(|other| &other.position)
+                        .collect();
+                    self.push(duplicate_argument(&arg.position, &name, &others));
+                }
+            }
+
+            if let Some(template_spec) = spec {
+                // the last path element is this template itself.
+                let ancestors = ancestor_specs(
+                    &self.path[..self.path.len().saturating_sub(1)],
+                    settings,
+                );
+                let name = template_spec.default_name();
+                if let Some(ref parents) = template_spec.parents {
+                    if !ancestors.iter().any(|a| parents.iter().any(|p| a.has_name(p))) {
+                        self.push(illegal_nesting(
+                            &template.position,
+                            format!("`{}` must be used inside another template!", name),
+                            format!(
+                                "Use `{}` only inside one of: {}",
+                                name,
+                                parents
+                                    .iter()
+                                    .map(|p| format!("`{}`", p))
+                                    .collect::<Vec<String>>()
+                                    .join(", ")
+                            ),
+                        ));
+                    }
+                }
+                // templates which are not nestable may not be used anywhere
+                // inside themselves, the nearest template restricting its
+                // children decides about all other templates.
+                let parent = ancestors
+                    .iter()
+                    .find(|a| !template_spec.nestable && a.default_name() == name)
+                    .or_else(|| ancestors.iter().find(|a| a.children.is_some()))
+                    .filter(|a| !template_spec.allowed_in(a));
+                if let Some(parent) = parent {
+                    self.push(illegal_nesting(
+                        &template.position,
+                        format!(
+                            "`{}` is not allowed inside `{}`!",
+                            name,
+                            parent.default_name()
+                        ),
+                        format!(
+                            "Move `{}` out of `{}`.",
+                            name,
+                            parent.default_name()
+                        ),
+                    ));
+                }
+
                 // make platform-specific modifications to the lint.
                 #[allow(unused_variables, unused_mut)]
                 fn add_spec_lint<'e, 's: 'e>(
                     rule: &mut Rule<'e, 's>,
                     mut lint: Lint,
-                    spec: &spec_meta::TemplateSpec,
+                    spec: &TemplateSpec,
                 ) {
                     #[cfg(feature = "web")]
                     {
                         lint.solution
                             .push_str("<details class=\"template-doc\"><summary>Template Documentation:</summary>\n");
                         lint.solution
-                            .push_str(&markdown::template_description(&spec, 1));
+                            .push_str(&markdown(&spec, 1));
                         lint.solution.push_str("\n</details>");
                     }
                     rule.push(lint)
                 }
 
-                if parse_template(&template).is_none() {
-                    for arg_spec in &template_spec.attributes {
-                        let exists = find_arg(&template.content, &arg_spec.names).is_some();
-                        if !exists && arg_spec.priority == spec_meta::Priority::Required {
-                            add_spec_lint(
-                                self,
-                                missing_argument(
-                                    &template.position,
-                                    &arg_spec.default_name().trim().to_lowercase(),
-                                ),
-                                &template_spec,
-                            );
+                // find the given argument for an argument specification.
+                let find_spec_arg = |arg_spec: &ArgumentSpec| {
+                    template.content.iter().find(|arg| {
+                        if let Element::TemplateArgument(ref arg) = **arg {
+                            arg_spec.has_name(&arg.name)
+                        } else {
+                            false
                         }
+                    })
+                };
+
+                let mut missing = false;
+                for arg_spec in &template_spec.arguments {
+                    let exists = find_spec_arg(arg_spec).is_some();
+                    if !exists && arg_spec.priority == Priority::Required {
+                        add_spec_lint(
+                            self,
+                            missing_argument(
+                                &template.position,
+                                &arg_spec.default_name().trim().to_lowercase(),
+                            ),
+                            &template_spec,
+                        );
+                        missing = true;
                     }
+                }
+                if missing {
                     return Ok(true);
-                };
+                }
 
                 let default_name = template_spec.default_name().trim().to_lowercase();
                 if template_name != default_name {
@@ -324,11 +655,11 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                     );
                 }
 
-                for arg_spec in &template_spec.attributes {
+                for arg_spec in &template_spec.arguments {
                     let default_argname = arg_spec.default_name().trim().to_lowercase();
 
                     if let Some(&Element::TemplateArgument(ref arg)) =
-                        find_arg(&template.content, &arg_spec.names)
+                        find_spec_arg(arg_spec)
                     {
                         let actual_name = &arg.name;
                         if actual_name != &default_argname {
@@ -345,7 +676,7 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                             );
                         }
 
-                        if let Err(error) = (arg_spec.predicate)(&arg.value) {
+                        if let Err(error) = arg_spec.check(&arg.value) {
                             add_spec_lint(
                                 self,
                                 illegal_content(
@@ -365,7 +696,7 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
 
                 // find unspecified arguments
                 let allowed_args: Vec<&str> = template_spec
-                    .attributes
+                    .arguments
                     .iter()
                     .map(|a| a.default_name())
                     .collect();
@@ -373,12 +704,28 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                 for argument in &template.content {
                     if let Element::TemplateArgument(ref arg) = *argument {
                         let name = arg.name.trim().to_lowercase();
-                        let has_spec = template_spec
-                            .attributes
-                            .iter()
-                            .any(|arg_spec| arg_spec.names.contains(&name));
+                        let has_spec = template_spec.argument(&name).is_some();
 
                         if !has_spec {
+                            // positional arguments are no spelling mistakes.
+                            let suggestions = if name.chars().all(|c| c.is_ascii_digit()) {
+                                vec![]
+                            } else {
+                                unique(
+                                    closest_matches(
+                                        &name,
+                                        template_spec.arguments.iter().flat_map(|a| a.names()),
+                                    )
+                                    .into_iter()
+                                    .filter_map(|n| template_spec.argument(n))
+                                    .map(|a| a.default_name()),
+                                )
+                            };
+                            let replacements = if suggestions.len() == 1 {
+                                vec![rename_argument(arg, suggestions[0])]
+                            } else {
+                                vec![]
+                            };
                             add_spec_lint(
                                 self,
                                 illegal_argument(
@@ -386,6 +733,8 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                                     &name,
                                     &template_name,
                                     allowed_args.as_slice(),
+                                    &suggestions,
+                                    replacements,
                                 ),
                                 &template_spec,
                             );
@@ -393,9 +742,103 @@ impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CheckTemplates<'e> {
                     }
                 }
             } else {
-                self.push(template_not_allowed(&template.position, &template_name));
+                let suggestions = unique(
+                    closest_matches(
+                        &template_name,
+                        settings.template_spec.iter().flat_map(|t| t.names()),
+                    )
+                    .into_iter()
+                    .filter_map(|n| spec_of(&settings.template_spec, n))
+                    .map(|t| t.default_name()),
+                );
+                let replacements = if suggestions.len() == 1 {
+                    vec![Replacement {
+                        position: name_position(template),
+                        text: suggestions[0].to_string(),
+                    }]
+                } else {
+                    vec![]
+                };
+                self.push(template_not_allowed(
+                    &template.position,
+                    &template_name,
+                    &suggestions,
+                    replacements,
+                ));
             }
         }
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(source: &str, kind: LintKind) -> Vec<Lint> {
+        let mut settings = Settings::default();
+        settings.template_spec = serde_yaml::from_str(
+            "
+- name: Beweisschritt
+  arguments:
+    - name: ziel
+    - name: begründung
+- name: Satz
+",
+        )
+        .unwrap();
+        let tree = parse(source).unwrap();
+        let mut rule = CheckTemplates::default();
+        let lints = tree.check(&mut rule, &settings).unwrap();
+        lints.iter().filter(|l| l.kind == kind).cloned().collect()
+    }
+
+    fn illegal_arguments(source: &str) -> Vec<Lint> {
+        lints(source, LintKind::IllegalArgument)
+    }
+
+    #[test]
+    fn rename_template_fix() {
+        let source = "{{ Beweisschrit |ziel=Ziel}}";
+        let lints = lints(source, LintKind::TemplateNotAllowed);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(
+            apply_replacements(source, &lints[0].replacements),
+            "{{ Beweisschritt |ziel=Ziel}}"
+        );
+    }
+
+    #[test]
+    fn short_template_names() {
+        let lints = lints("{{Tanz}}", LintKind::TemplateNotAllowed);
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].replacements.is_empty());
+    }
+
+    #[test]
+    fn rename_argument_fix() {
+        let source = "{{Beweisschritt|zieel=Ziel|begründung=Grund}}";
+        let lints = illegal_arguments(source);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].solution, "Did you mean `ziel`?");
+
+        // the replacement reaches from the `|` to the value.
+        let position = &lints[0].replacements[0].position;
+        assert_eq!(&source[position.start.offset..position.end.offset], "|zieel=");
+        assert_eq!(
+            apply_replacements(source, &lints[0].replacements),
+            "{{Beweisschritt|ziel=Ziel|begründung=Grund}}"
+        );
+    }
+
+    #[test]
+    fn rename_empty_argument_fix() {
+        let source = "{{Beweisschritt|zieel=|begründung=Grund}}";
+        let lints = illegal_arguments(source);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(
+            apply_replacements(source, &lints[0].replacements),
+            "{{Beweisschritt|ziel=|begründung=Grund}}"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use crate::plugin::is_namespaced;
+use crate::preamble::*;
+use crate::query::Selector;
+use regex::Regex;
+use std::slice;
+
+rule_impl!(CustomRules, "Checks the custom rules given in the settings."
+=> examples:
+);
+
+/// A simple project-specific rule given in the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRule {
+    /// Namespaced lint code, like `house::no-noprint`.
+    pub code: String,
+    /// Selector the element must match, like `template[name=formel] > list`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    /// Element types to check, all types if empty.
+    #[serde(default)]
+    pub elements: Vec<ElementType>,
+    /// Name of the template, html tag or markup (like `math`) to check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The element must be inside one of these element types.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inside: Vec<ElementType>,
+    /// The element must not be inside one of these element types.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_inside: Vec<ElementType>,
+    /// Regular expression the plain text of the element must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub solution: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    /// Replacement for matches of `pattern` (with `$1` for groups), offered
+    /// as automatic fix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// The compiled pattern, see `compile`.
+    #[serde(skip)]
+    pub regex: Option<Regex>,
+    /// The parsed selector, see `compile`.
+    #[serde(skip)]
+    pub parsed_selector: Option<Selector>,
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+impl CustomRule {
+    /// Check the code and compile the pattern and selector of this rule.
+    /// Rules are compiled when the settings are read.
+    pub fn compile(&mut self) -> io::Result<()> {
+        if !is_namespaced(&self.code) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "code of custom rule `{}` must have the form `namespace::name`!",
+                    self.code
+                ),
+            ));
+        }
+        if let Some(ref pattern) = self.pattern {
+            let regex = Regex::new(pattern).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid pattern of custom rule `{}`: {}", self.code, e),
+                )
+            })?;
+            self.regex = Some(regex);
+        }
+        if let Some(ref selector) = self.selector {
+            let selector = selector.parse::<Selector>().map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid selector of custom rule `{}`: {}", self.code, e),
+                )
+            })?;
+            self.parsed_selector = Some(selector);
+        }
+        Ok(())
+    }
+
+    // does the name of the element match?
+    fn name_matches(&self, element: &Element) -> bool {
+        let expected = match self.name {
+            Some(ref name) => name.trim().to_lowercase(),
+            None => return true,
+        };
+        let actual = match *element {
+            Element::Template(ref template) => extract_plain_text(&template.name),
+            Element::HtmlTag(ref tag) => tag.name.clone(),
+            Element::Formatted(ref formatted) => format!("{:?}", formatted.markup),
+            _ => return false,
+        };
+        actual.trim().to_lowercase() == expected
+    }
+
+    /// Check an element with its ancestors. Fails if the rule has a pattern
+    /// or selector, but was not compiled.
+    pub fn check(&self, element: &Element, ancestors: &[&Element]) -> io::Result<Option<Lint>> {
+        let element_type = ElementType::from(element);
+        let has_ancestor = |types: &[ElementType]| {
+            ancestors
+                .iter()
+                .any(|a| types.contains(&ElementType::from(*a)))
+        };
+
+        if (!self.elements.is_empty() && !self.elements.contains(&element_type))
+            || !self.name_matches(element)
+            || (!self.inside.is_empty() && !has_ancestor(&self.inside))
+            || has_ancestor(&self.not_inside)
+        {
+            return Ok(None);
+        }
+
+        if (self.selector.is_some() && self.parsed_selector.is_none())
+            || (self.pattern.is_some() && self.regex.is_none())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("custom rule `{}` was not compiled!", self.code),
+            ));
+        }
+
+        if let Some(ref selector) = self.parsed_selector {
+            let mut path = ancestors.to_vec();
+            path.push(element);
+            if !selector.matches(&path) {
+                return Ok(None);
+            }
+        }
+
+        let regex = self.regex.as_ref();
+        let slice = slice::from_ref(element);
+        if let Some(regex) = regex {
+            if !regex.is_match(&extract_plain_text(slice)) {
+                return Ok(None);
+            }
+        }
+
+        let mut replacements = vec![];
+        if let (Some(regex), Some(replacement)) = (regex, &self.replacement) {
+            if let Some(source) = to_wikitext(slice) {
+                replacements.push(Replacement {
+                    position: element.get_position().clone(),
+                    text: regex
+                        .replace_all(&source, replacement.as_str())
+                        .into_owned(),
+                });
+            }
+        }
+        Ok(Some(Lint {
+            position: element.get_position().clone(),
+            explanation: self.message.clone(),
+            explanation_long: if self.description.is_empty() {
+                self.message.clone()
+            } else {
+                self.description.clone()
+            },
+            solution: self.solution.clone(),
+            severity: self.severity.clone(),
+            kind: LintKind::Custom,
+            replacements,
+            code: Some(self.code.clone()),
+        }))
+    }
+}
+
+impl<'e, 's> Traversion<'e, &'s Settings<'s>> for CustomRules<'e> {
+    path_impl!();
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        // the last path element is the element itself.
+        let ancestors = &self.path[..self.path.len().saturating_sub(1)];
+        let mut lints = vec![];
+        for rule in &settings.custom_rules {
+            if let Some(lint) = rule.check(root, ancestors)? {
+                lints.push(lint);
+            }
+        }
+        for lint in lints {
+            self.push(lint);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(rules: &str, source: &str) -> Vec<Lint> {
+        let mut settings = Settings::default();
+        settings.custom_rules = serde_yaml::from_str(rules).unwrap();
+        for rule in &mut settings.custom_rules {
+            rule.compile().unwrap();
+        }
+        let tree = parse(source).unwrap();
+        let mut rule = CustomRules::default();
+        tree.check(&mut rule, &settings).unwrap().clone()
+    }
+
+    #[test]
+    fn forbidden_template() {
+        let rules = "
+- code: house::no-noprint
+  elements: [Template]
+  name: Noprint
+  message: Do not use Noprint.
+";
+        let lints = check(rules, "{{Noprint|text}} {{Formel|<math>x</math>}}");
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].code(), "house::no-noprint");
+    }
+
+    #[test]
+    fn text_pattern() {
+        let rules = "
+- code: house::thin-space
+  elements: [Text]
+  pattern: 'z\\.B\\.'
+  replacement: 'z.&#8239;B.'
+  message: Use a thin space in abbreviations.
+  severity: info
+";
+        let lints = check(rules, "Zahlen, z.B. drei.");
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].severity, Severity::Info);
+        assert_eq!(lints[0].replacements[0].text, "Zahlen, z.&#8239;B. drei.");
+    }
+
+    #[test]
+    fn ancestor_condition() {
+        let rules = "
+- code: house::no-math-in-headings
+  elements: [Formatted]
+  name: math
+  inside: [Heading]
+  message: Headings must not contain formulas.
+";
+        let lints = check(rules, "== The <math>x</math> ==\n<math>y</math>\n");
+        assert_eq!(lints.len(), 1);
+    }
+
+    #[test]
+    fn selector_condition() {
+        let rules = "
+- code: house::no-lists-in-formulas
+  selector: template[name=formel] > list
+  message: Formulas must not contain lists.
+";
+        let lints = check(rules, "{{Formel|\n* a\n}}\n\n* b\n");
+        assert_eq!(lints.len(), 1);
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let mut rules: Vec<CustomRule> =
+            serde_yaml::from_str("- code: no-noprint\n  message: Do not use Noprint.\n")
+                .unwrap();
+        assert!(rules[0].compile().is_err());
+
+        let mut settings = Settings::default();
+        settings.custom_rules = serde_yaml::from_str(
+            "- code: house::thin-space\n  pattern: 'z\\.B\\.'\n  message: Use a thin space.\n",
+        )
+        .unwrap();
+        let tree = parse("z.B.").unwrap();
+        let mut rule = CustomRules::default();
+        assert!(tree.check(&mut rule, &settings).is_err());
+    }
+}
@@ -1,17 +1,26 @@
-use crate::lint::{Example, LintKind};
+use crate::lint::Example;
 use crate::rule::*;
 
+mod check_external_links;
+mod check_files;
 mod check_formulas;
 mod check_headings;
 mod check_html;
 mod check_lists;
 mod check_templates;
+mod custom_rules;
+mod template_style;
 
+pub use self::check_external_links::CheckExternalLinks;
+pub use self::check_files::CheckFiles;
+pub(crate) use self::check_files::missing_file;
 pub use self::check_formulas::CheckFormulas;
 pub use self::check_headings::CheckHeadings;
 pub use self::check_html::CheckHtml;
 pub use self::check_lists::CheckLists;
 pub use self::check_templates::CheckTemplates;
+pub use self::custom_rules::{CustomRule, CustomRules};
+pub use self::template_style::TemplateStyle;
 
 macro_rules! register {
     ($list:ident, $t1:tt :: $t2:tt) => {
@@ -27,17 +36,21 @@ pub fn get_rules<'e, 's: 'e>() -> Vec<Box<Rule<'e, 's>>> {
     register!(rules, check_templates::CheckTemplates);
     register!(rules, check_formulas::CheckFormulas);
     register!(rules, check_html::CheckHtml);
+    register!(rules, check_files::CheckFiles);
+    register!(rules, check_external_links::CheckExternalLinks);
+    register!(rules, template_style::TemplateStyle);
+    register!(rules, custom_rules::CustomRules);
     rules
 }
 
-/// Find all examples for lints of a given kind.
+/// Find all examples for lints of a given code (see `Lint::code`).
 pub fn get_examples<'e, 'r, 's: 'e>(
     rules: &'r [Box<Rule<'e, 's>>],
-    kind: LintKind,
+    code: &str,
 ) -> Vec<&'r Example> {
     let mut result = vec![];
     for rule in rules {
-        result.append(&mut rule.examples().iter().filter(|e| e.kind == kind).collect());
+        result.append(&mut rule.examples().iter().filter(|e| e.code() == code).collect());
     }
     result
 }
@@ -0,0 +1,280 @@
+use crate::preamble::*;
+use crate::template_spec::{spec_of, Priority, TemplateSpec};
+
+rule_impl!(TemplateStyle, "Checks the formatting of template calls (opt-in)."
+=> optional
+=> interests: [Template]
+=> examples:
+    argument_order,
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+     |teilaufgabe1-lösung=42\n\
+     |teilaufgabe1=Was ist der Sinn des Lebens?\n\
+     }}",
+    "The solution is given before the exercise, which is not the order of the \
+     template documentation.",
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+     |teilaufgabe1=Was ist der Sinn des Lebens?\n\
+     |teilaufgabe1-lösung=42\n\
+     }}",
+    "The arguments are given in the documented order."
+    => LintKind::TemplateArgumentOrder
+;
+    mixed_layout,
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe|teilaufgabe1=Was ist der Sinn des Lebens?\n\
+     |teilaufgabe1-lösung=42\n\
+     }}",
+    "The template spans multiple lines, but the first argument is on the same \
+     line as the template name.",
+    "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+     |teilaufgabe1=Was ist der Sinn des Lebens?\n\
+     |teilaufgabe1-lösung=42\n\
+     }}",
+    "Every argument is on its own line."
+    => LintKind::TemplateLayout
+;
+    empty_argument,
+    "{{Formel|<math>x^2</math>|}}",
+    "The template call ends with an empty argument.",
+    "{{Formel|<math>x^2</math>}}",
+    "The empty argument was removed."
+    => LintKind::EmptyTemplateArgument
+);
+
+fn style_lint(
+    position: &Span,
+    kind: LintKind,
+    explanation: String,
+    fix: Option<Replacement>,
+) -> Lint {
+    let (explanation_long, solution) = match kind {
+        LintKind::TemplateArgumentOrder => (
+            "Giving template arguments in the order of the template documentation \
+             makes template calls easier to read and to compare.",
+            "Reorder the arguments as in the template documentation.",
+        ),
+        LintKind::TemplateLayout => (
+            "A template call should either fit on one line or have every argument \
+             on its own line, starting with `|`.",
+            "Put every argument on its own line.",
+        ),
+        _ => (
+            "Empty optional arguments have no effect and only clutter the template \
+             call.",
+            "Remove the empty argument.",
+        ),
+    };
+    Lint {
+        position: position.clone(),
+        explanation,
+        explanation_long: explanation_long.into(),
+        solution: solution.into(),
+        severity: Severity::Info,
+        kind,
+        replacements: fix.into_iter().collect(),
+        code: None,
+    }
+}
+
+// is this argument empty and may be omitted?
+fn is_omittable(arg: &TemplateArgument, spec: Option<&TemplateSpec>) -> bool {
+    let required = spec
+        .and_then(|s| s.argument(&arg.name))
+        .map(|a| a.priority == Priority::Required)
+        .unwrap_or(false);
+    !required && extract_plain_text(&arg.value).trim().is_empty() && arg.value.len() <= 1
+}
+
+// position of an argument in the specification.
+fn spec_index(arg: &TemplateArgument, spec: Option<&TemplateSpec>) -> Option<usize> {
+    spec.and_then(|s| s.arguments.iter().position(|a| a.has_name(&arg.name)))
+}
+
+// the template call with the given arguments (in the documented order, if
+// `sort` is set), if it can be reproduced.
+fn format_template(
+    template: &Template,
+    arguments: &[&TemplateArgument],
+    spec: Option<&TemplateSpec>,
+    multiline: bool,
+    sort: bool,
+) -> Option<Replacement> {
+    let mut arguments: Vec<&&TemplateArgument> = arguments.iter().collect();
+    if sort {
+        // stable sort, unknown arguments stay at the end.
+        arguments.sort_by_key(|arg| spec_index(arg, spec).unwrap_or(usize::max_value()));
+    }
+
+    let mut result = format!("{{{{{}", to_wikitext(&template.name)?.trim());
+    let mut positional = 0;
+    for arg in arguments {
+        let value = to_wikitext(&arg.value)?;
+        let value = value.trim();
+        result.push_str(if multiline { "\n|" } else { "|" });
+
+        let name = arg.name.trim();
+        if name == (positional + 1).to_string() && !value.contains('=') {
+            positional += 1;
+        } else {
+            result.push_str(name);
+            result.push('=');
+        }
+        result.push_str(value);
+    }
+    result.push_str(if multiline { "\n}}" } else { "}}" });
+    Some(Replacement {
+        position: template.position.clone(),
+        text: result,
+    })
+}
+
+impl<'e, 's> Traversion<'e, &'s Settings<'s>> for TemplateStyle<'e> {
+    path_impl!();
+
+    fn work(
+        &mut self,
+        root: &'e Element,
+        settings: &Settings,
+        _: &mut io::Write,
+    ) -> io::Result<bool> {
+        let template = if let Element::Template(ref template) = *root {
+            template
+        } else {
+            return Ok(true);
+        };
+        let name = extract_plain_text(&template.name).trim().to_lowercase();
+        if name.starts_with('#') {
+            return Ok(true);
+        }
+        let spec = spec_of(&settings.template_spec, &name);
+        let arguments: Vec<&TemplateArgument> = template
+            .content
+            .iter()
+            .filter_map(|e| {
+                if let Element::TemplateArgument(ref arg) = *e {
+                    Some(arg)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let multiline = template.position.start.line != template.position.end.line;
+        let fix = format_template(template, &arguments, spec, multiline, true);
+
+        let mut previous: Option<(usize, &TemplateArgument)> = None;
+        for arg in &arguments {
+            if let Some(index) = spec_index(arg, spec) {
+                if let Some((previous_index, previous_arg)) = previous {
+                    if index < previous_index {
+                        self.push(style_lint(
+                            &template.position,
+                            LintKind::TemplateArgumentOrder,
+                            format!(
+                                "The argument `{}` should be given before `{}`.",
+                                arg.name.trim(),
+                                previous_arg.name.trim()
+                            ),
+                            fix.clone(),
+                        ));
+                        break;
+                    }
+                }
+                previous = Some((index, arg));
+            }
+        }
+
+        if multiline {
+            let mut lines = vec![template.position.start.line];
+            for arg in &arguments {
+                let line = arg.position.start.line;
+                if lines.contains(&line) {
+                    self.push(style_lint(
+                        &template.position,
+                        LintKind::TemplateLayout,
+                        format!(
+                            "The argument `{}` should be on its own line.",
+                            arg.name.trim()
+                        ),
+                        fix.clone(),
+                    ));
+                    break;
+                }
+                lines.push(line);
+            }
+        }
+
+        // only remove the empty arguments, keep the order.
+        let kept: Vec<&TemplateArgument> = arguments
+            .iter()
+            .cloned()
+            .filter(|arg| !is_omittable(arg, spec))
+            .collect();
+        let removal = format_template(template, &kept, spec, multiline, false);
+        for arg in &arguments {
+            if is_omittable(arg, spec) {
+                self.push(style_lint(
+                    &arg.position,
+                    LintKind::EmptyTemplateArgument,
+                    format!("The argument `{}` is empty.", arg.name.trim()),
+                    removal.clone(),
+                ));
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(source: &str, kind: LintKind) -> String {
+        let settings = Settings::default();
+        let tree = parse(source).unwrap();
+        let mut rule = TemplateStyle::default();
+        let lints = tree.check(&mut rule, &settings).unwrap();
+        let lint = lints.iter().find(|l| l.kind == kind).unwrap();
+        apply_replacements(source, &lint.replacements)
+    }
+
+    #[test]
+    fn empty_argument_fix() {
+        let source = "Text {{Formel|<math>x^2</math>|}} text";
+        assert_eq!(
+            fixed(source, LintKind::EmptyTemplateArgument),
+            "Text {{Formel|<math>x^2</math>}} text"
+        );
+    }
+
+    #[test]
+    fn argument_order_fix() {
+        let source = "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+                      |teilaufgabe1-lösung=42\n\
+                      |teilaufgabe1=Frage\n\
+                      }}";
+        assert_eq!(
+            fixed(source, LintKind::TemplateArgumentOrder),
+            "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+             |teilaufgabe1=Frage\n\
+             |teilaufgabe1-lösung=42\n\
+             }}"
+        );
+    }
+
+    #[test]
+    fn argument_order_keeps_empty_arguments() {
+        let source = "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+                      |teilaufgabe1-lösung=42\n\
+                      |teilaufgabe1=Frage\n\
+                      |notiz=\n\
+                      }}";
+        assert_eq!(
+            fixed(source, LintKind::TemplateArgumentOrder),
+            "{{:Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe\n\
+             |teilaufgabe1=Frage\n\
+             |teilaufgabe1-lösung=42\n\
+             |notiz=\n\
+             }}"
+        );
+    }
+}
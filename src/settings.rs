@@ -1,5 +1,10 @@
-use mfnf_template_spec::{spec, spec_meta::TemplateSpec};
+use crate::rules::CustomRule;
+use crate::template_spec::{builtin, TemplateSpec};
+use crate::transformations::Normalization;
 use mwparser_utils::CachedTexChecker;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashSet};
 
 /// Rule metadata.
 #[derive(Debug, Serialize, PartialEq, Clone, Deserialize)]
@@ -8,28 +13,186 @@ pub struct RuleMeta {
     pub description: String,
 }
 
-/// Settings for linter rules.
+/// Settings for linter rules. Missing fields have their default value.
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings<'p> {
+    /// Names of opt-in rules to run in addition to the default rules.
+    pub optional_rules: Vec<String>,
     /// Maximum allowed depth of a heading.
     pub max_heading_depth: usize,
-    /// List of allowed html tags.
-    pub html_whitelist: Vec<String>,
+    /// Allowed html tags, mapped to their allowed attributes.
+    #[serde(deserialize_with = "html_whitelist")]
+    pub html_whitelist: BTreeMap<String, Vec<String>>,
+    /// Allowed values for the `group` attribute of `<ref>`.
+    pub ref_groups: Vec<String>,
+    /// Common prefix of all page names of the project. Links to pages
+    /// without this prefix are not checked against the project.
+    pub page_prefix: String,
+    /// All spellings of the file namespace known to the wiki. Links with
+    /// one of these prefixes (in any case) embed a file.
+    pub file_namespace_aliases: Vec<String>,
+    /// Allowed spellings of the file namespace.
+    pub file_namespaces: Vec<String>,
+    /// Allowed options for embedded files (keywords or keys of `key=value`).
+    pub file_options: Vec<String>,
+    /// Allow explicit image sizes like `300px`.
+    pub file_allow_size: bool,
+    /// File extensions supported by the export.
+    pub file_types: Vec<String>,
+    /// If not empty, external links may only point to these domains.
+    pub link_allowlist: Vec<String>,
+    /// External links must not point to these domains.
+    pub link_denylist: Vec<String>,
+    /// External links must use `https://`.
+    pub require_https: bool,
+    /// Language code of the wiki, used for interwiki links.
+    pub wiki_language: String,
+    /// Simple project-specific rules, compiled when they are read.
+    #[serde(deserialize_with = "compiled_rules")]
+    pub custom_rules: Vec<CustomRule>,
+    /// Transformations applied to documents before checking, in this order.
+    pub normalization: Vec<Normalization>,
+    /// Names of all available media files, if known.
+    #[serde(skip)]
+    pub media_files: Option<HashSet<String>>,
     /// Object performing formula verification.
     #[serde(skip)]
     pub tex_checker: Option<CachedTexChecker>,
     /// Specification of allowed templates.
-    #[serde(skip)]
+    #[serde(skip, default = "builtin")]
     pub template_spec: Vec<TemplateSpec<'p>>,
 }
 
 impl<'p> Default for Settings<'p> {
     fn default() -> Self {
         Settings {
+            optional_rules: vec![],
             max_heading_depth: 4,
-            html_whitelist: vec!["section".into(), "dfn".into(), "ref".into()],
+            html_whitelist: default_html_whitelist(),
+            ref_groups: vec!["note".into()],
+            page_prefix: "Mathe für Nicht-Freaks:".into(),
+            file_namespace_aliases: ["Datei", "File", "Bild", "Image"]
+                .iter()
+                .map(|n| n.to_string())
+                .collect(),
+            file_namespaces: vec!["Datei".into(), "File".into()],
+            file_options: ["mini", "miniatur", "thumb", "hochkant", "upright", "alt", "link"]
+                .iter()
+                .map(|o| o.to_string())
+                .collect(),
+            file_allow_size: false,
+            file_types: ["svg", "png", "jpg", "jpeg", "gif"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            link_allowlist: vec![],
+            link_denylist: ["bit.ly", "goo.gl", "tinyurl.com"]
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+            require_https: true,
+            wiki_language: "de".into(),
+            custom_rules: vec![],
+            normalization: vec![Normalization::TemplateList],
+            media_files: None,
             tex_checker: None,
-            template_spec: spec::<'p>(),
+            template_spec: builtin::<'p>(),
         }
     }
 }
+
+fn default_html_whitelist() -> BTreeMap<String, Vec<String>> {
+    [
+        ("section", vec!["begin", "end"]),
+        ("dfn", vec!["title"]),
+        ("ref", vec!["name", "group"]),
+        ("references", vec!["group"]),
+    ]
+    .iter()
+    .map(|(tag, attrs)| {
+        (
+            tag.to_string(),
+            attrs.iter().map(|a| a.to_string()).collect(),
+        )
+    })
+    .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HtmlWhitelist {
+    Attributes(BTreeMap<String, Vec<String>>),
+    Tags(Vec<String>),
+}
+
+// older settings only list the allowed tags, these keep their default
+// attributes.
+fn html_whitelist<'de, D>(deserializer: D) -> Result<BTreeMap<String, Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match HtmlWhitelist::deserialize(deserializer)? {
+        HtmlWhitelist::Attributes(whitelist) => whitelist,
+        HtmlWhitelist::Tags(tags) => {
+            let defaults = default_html_whitelist();
+            tags.into_iter()
+                .map(|tag| {
+                    let attributes = defaults.get(&tag).cloned().unwrap_or_default();
+                    (tag, attributes)
+                })
+                .collect()
+        }
+    })
+}
+
+// custom rules with a checked code and compiled patterns and selectors.
+fn compiled_rules<'de, D>(deserializer: D) -> Result<Vec<CustomRule>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut rules = Vec::<CustomRule>::deserialize(deserializer)?;
+    for rule in &mut rules {
+        rule.compile().map_err(D::Error::custom)?;
+    }
+    for (index, rule) in rules.iter().enumerate() {
+        if rules[..index].iter().any(|r| r.code == rule.code) {
+            return Err(D::Error::custom(format!(
+                "custom rule code `{}` is used twice!",
+                rule.code
+            )));
+        }
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_config() {
+        // written by `--dump-config` before attributes and other settings
+        // were added.
+        let config = "---\nmax_heading_depth: 3\n\
+                      html_whitelist:\n  - section\n  - dfn\n  - sub\n";
+        let settings: Settings = serde_yaml::from_str(config).unwrap();
+        assert_eq!(settings.max_heading_depth, 3);
+        assert_eq!(settings.html_whitelist["section"], vec!["begin", "end"]);
+        assert!(settings.html_whitelist["sub"].is_empty());
+        assert!(!settings.html_whitelist.contains_key("ref"));
+        assert_eq!(settings.ref_groups, Settings::default().ref_groups);
+        assert!(!settings.template_spec.is_empty());
+    }
+
+    #[test]
+    fn compiled_custom_rules() {
+        let config = "custom_rules:\n  - code: house::thin-space\n    pattern: 'z\\.B\\.'\n    \
+                      message: Use a thin space.\n";
+        let settings: Settings = serde_yaml::from_str(config).unwrap();
+        assert!(settings.custom_rules[0].regex.is_some());
+
+        let config = config.replace("house::", "");
+        assert!(serde_yaml::from_str::<Settings>(&config).is_err());
+    }
+}
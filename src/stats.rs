@@ -0,0 +1,145 @@
+use crate::lint::Lint;
+use crate::settings::Settings;
+use crate::template_spec::spec_of;
+use crate::utils::*;
+use mediawiki_parser::*;
+use mwparser_utils::extract_plain_text;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Usage counts aggregated over a set of documents.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Number of documents.
+    pub documents: usize,
+    /// Template calls by the default name of the template.
+    pub templates: BTreeMap<String, usize>,
+    /// Uses of deprecated template names and argument names
+    /// (as `template|argument`).
+    pub deprecated_names: BTreeMap<String, usize>,
+    /// Template arguments by template and default argument name.
+    pub arguments: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Lints by code.
+    pub lints: BTreeMap<String, usize>,
+    /// Html tags by tag name.
+    pub html_tags: BTreeMap<String, usize>,
+}
+
+fn count(map: &mut BTreeMap<String, usize>, key: &str) {
+    *map.entry(key.to_string()).or_insert(0) += 1;
+}
+
+impl Stats {
+    /// Add the usage counts of a document and its lints.
+    pub fn add_document(&mut self, root: &Element, lints: &[Lint], settings: &Settings) {
+        self.documents += 1;
+
+        for element in collect_elements(root, |_| true) {
+            match *element {
+                Element::Template(ref template) => self.add_template(template, settings),
+                Element::HtmlTag(ref tag) => count(&mut self.html_tags, &tag.name.to_lowercase()),
+                _ => (),
+            }
+        }
+
+        for lint in lints {
+            count(&mut self.lints, &lint.code());
+        }
+    }
+
+    fn add_template(&mut self, template: &Template, settings: &Settings) {
+        let name = extract_plain_text(&template.name);
+        let name = name.trim();
+        if name.starts_with('#') {
+            return;
+        }
+        let spec = spec_of(&settings.template_spec, name);
+        let default_name = spec.map(|s| s.default_name()).unwrap_or(name);
+        count(&mut self.templates, default_name);
+        if default_name.to_lowercase() != name.to_lowercase() {
+            count(&mut self.deprecated_names, name);
+        }
+
+        let arguments = self
+            .arguments
+            .entry(default_name.to_string())
+            .or_insert_with(BTreeMap::new);
+        for element in &template.content {
+            if let Element::TemplateArgument(ref arg) = *element {
+                let arg_name = arg.name.trim();
+                let arg_spec = spec.and_then(|s| s.argument(arg_name));
+                let arg_default = arg_spec.map(|a| a.default_name()).unwrap_or(arg_name);
+                count(arguments, arg_default);
+                if arg_default.to_lowercase() != arg_name.to_lowercase() {
+                    count(
+                        &mut self.deprecated_names,
+                        &format!("{}|{}", default_name, arg_name),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Render the statistics as markdown tables, most used first.
+    pub fn markdown(&self) -> String {
+        fn table(out: &mut String, title: &str, header: &str, map: &BTreeMap<String, usize>) {
+            let mut rows: Vec<(&String, &usize)> = map.iter().collect();
+            rows.sort_by(|a, b| b.1.cmp(a.1));
+
+            writeln!(out, "## {}\n", title).unwrap();
+            writeln!(out, "| {} | count |", header).unwrap();
+            writeln!(out, "|----|----:|").unwrap();
+            for (key, value) in rows {
+                writeln!(out, "| `{}` | {} |", key.replace('|', "\\|"), value).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        let mut out = String::new();
+        writeln!(out, "# Usage Statistics\n").unwrap();
+        writeln!(out, "{} documents.\n", self.documents).unwrap();
+        table(&mut out, "Templates", "template", &self.templates);
+        table(
+            &mut out,
+            "Deprecated Names",
+            "name",
+            &self.deprecated_names,
+        );
+        let arguments: BTreeMap<String, usize> = self
+            .arguments
+            .iter()
+            .flat_map(|(template, args)| {
+                args.iter()
+                    .map(move |(arg, n)| (format!("{}|{}", template, arg), *n))
+            })
+            .collect();
+        table(&mut out, "Template Arguments", "argument", &arguments);
+        table(&mut out, "Lints", "code", &self.lints);
+        table(&mut out, "HTML Tags", "tag", &self.html_tags);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_counts() {
+        let settings = Settings::default();
+        let mut stats = Stats::default();
+        for source in &[
+            "{{Formel|<math>x</math>}}<dfn>a</dfn>",
+            "{{formel|<math>y</math>}}{{Unknown|a=b}}",
+        ] {
+            stats.add_document(&parse(source).unwrap(), &[], &settings);
+        }
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.templates["Formel"], 2);
+        assert_eq!(stats.templates["Unknown"], 1);
+        assert_eq!(stats.arguments["Unknown"]["a"], 1);
+        assert_eq!(stats.html_tags["dfn"], 1);
+        assert!(stats.markdown().contains("| `Formel` | 2 |"));
+    }
+}
@@ -0,0 +1,714 @@
+use crate::utils::escape_html;
+use mediawiki_parser::*;
+use mfnf_template_spec::{is_plain_text, spec, spec_meta};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Why a template argument does not fulfill its predicate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredicateError<'e> {
+    /// The offending element, if known.
+    pub tree: Option<&'e Element>,
+    /// Explanation of the problem.
+    pub cause: String,
+}
+
+pub type PredicateResult<'e> = Result<(), PredicateError<'e>>;
+
+/// A property the content of a template argument must fulfill.
+pub type Predicate = Fn(&[Element]) -> PredicateResult;
+
+/// A predicate of the built-in predicate library.
+pub struct PredicateInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub function: fn(&[Element]) -> PredicateResult,
+}
+
+/// Predicates which can be used in template specification files.
+pub const PREDICATES: &[PredicateInfo] = &[
+    PredicateInfo {
+        name: "everything_is_allowed",
+        description: "Any content is allowed.",
+        function: everything_is_allowed,
+    },
+    PredicateInfo {
+        name: "is_plain_text",
+        description: "Only plain text without any markup is allowed.",
+        function: plain_text,
+    },
+    PredicateInfo {
+        name: "is_math_tag",
+        description: "Exactly one `<math>` element is allowed.",
+        function: is_math_tag,
+    },
+    PredicateInfo {
+        name: "is_inline",
+        description: "Only inline markup is allowed, no paragraphs, lists, \
+                      headings or tables.",
+        function: is_inline,
+    },
+    PredicateInfo {
+        name: "is_non_empty",
+        description: "The content must not be empty.",
+        function: is_non_empty,
+    },
+];
+
+/// Find a predicate of the predicate library by name.
+pub fn predicate_info(name: &str) -> Option<&'static PredicateInfo> {
+    PREDICATES.iter().find(|p| p.name == name)
+}
+
+fn everything_is_allowed(_: &[Element]) -> PredicateResult {
+    Ok(())
+}
+
+fn plain_text(content: &[Element]) -> PredicateResult {
+    is_plain_text(content).map_err(|e| PredicateError {
+        tree: e.tree,
+        cause: e.cause,
+    })
+}
+
+// is this element only whitespace?
+fn is_blank(element: &Element) -> bool {
+    if let Element::Text(ref text) = *element {
+        text.text.trim().is_empty()
+    } else {
+        false
+    }
+}
+
+fn is_math_tag(content: &[Element]) -> PredicateResult {
+    let mut elements = content.iter().filter(|e| !is_blank(e));
+    match (elements.next(), elements.next()) {
+        (Some(&Element::Formatted(ref f)), None) if f.markup == MarkupType::Math => Ok(()),
+        (first, second) => Err(PredicateError {
+            tree: second.or(first),
+            cause: "only a single math element is allowed here.".into(),
+        }),
+    }
+}
+
+fn is_inline(content: &[Element]) -> PredicateResult {
+    for element in content {
+        match *element {
+            Element::Paragraph(_)
+            | Element::List(_)
+            | Element::Heading(_)
+            | Element::Table(_)
+            | Element::Gallery(_) => {
+                return Err(PredicateError {
+                    tree: Some(element),
+                    cause: "block elements are not allowed here.".into(),
+                })
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn is_non_empty(content: &[Element]) -> PredicateResult {
+    if content.iter().all(is_blank) {
+        Err(PredicateError {
+            tree: None,
+            cause: "this argument must not be empty.".into(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Priority of a template argument.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Required,
+    Optional,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Optional
+    }
+}
+
+/// Specification of a template argument.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgumentSpec<'p> {
+    /// Preferred name of the argument.
+    pub name: String,
+    /// Other (deprecated) names of the argument.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Explanation of the argument.
+    #[serde(default)]
+    pub description: String,
+    /// Is this argument required?
+    #[serde(default)]
+    pub priority: Priority,
+    /// Name of the predicate the content must fulfill.
+    #[serde(rename = "predicate", default = "default_predicate")]
+    pub predicate_name: String,
+    /// The predicate itself, resolved by name.
+    #[serde(skip)]
+    pub predicate: Option<Box<Predicate + 'p>>,
+}
+
+fn default_predicate() -> String {
+    "everything_is_allowed".into()
+}
+
+/// Specification of a template.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateSpec<'p> {
+    /// Preferred name of the template.
+    pub name: String,
+    /// Other (deprecated) names of the template.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Explanation of the template.
+    #[serde(default)]
+    pub description: String,
+    /// The template arguments.
+    #[serde(default)]
+    pub arguments: Vec<ArgumentSpec<'p>>,
+    /// If given, the template may only be used inside one of these templates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parents: Option<Vec<String>>,
+    /// If given, only these templates may be used inside the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<String>>,
+    /// May the template be used inside itself?
+    #[serde(default = "default_nestable")]
+    pub nestable: bool,
+}
+
+fn default_nestable() -> bool {
+    true
+}
+
+// names are compared case-insensitive and without surrounding whitespace.
+fn name_matches(spec_name: &str, name: &str) -> bool {
+    spec_name.trim().to_lowercase() == name.trim().to_lowercase()
+}
+
+impl<'p> ArgumentSpec<'p> {
+    /// Preferred name first, then the aliases.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.aliases.iter().map(|a| a.as_str()));
+        names
+    }
+
+    pub fn default_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has_name(&self, name: &str) -> bool {
+        self.names().iter().any(|n| name_matches(n, name))
+    }
+
+    /// Check the content of an argument against the predicate.
+    pub fn check<'e>(&self, content: &'e [Element]) -> PredicateResult<'e> {
+        match self.predicate {
+            Some(ref predicate) => predicate(content),
+            None => Ok(()),
+        }
+    }
+
+    /// Look up the predicate by its name in the predicate library or among
+    /// the predicates of the built-in specification.
+    pub fn resolve_predicate(&mut self) -> io::Result<()> {
+        if let Some(info) = predicate_info(&self.predicate_name) {
+            self.predicate = Some(boxed(info.function));
+            return Ok(());
+        }
+        let predicate = builtin_predicate(&self.predicate_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unknown predicate `{}` for argument `{}`!",
+                    &self.predicate_name, &self.name
+                ),
+            )
+        })?;
+        self.predicate = Some(predicate);
+        Ok(())
+    }
+}
+
+impl<'p> TemplateSpec<'p> {
+    /// Preferred name first, then the aliases.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.aliases.iter().map(|a| a.as_str()));
+        names
+    }
+
+    pub fn default_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has_name(&self, name: &str) -> bool {
+        self.names().iter().any(|n| name_matches(n, name))
+    }
+
+    /// Find the specification of an argument by one of its names.
+    pub fn argument(&self, name: &str) -> Option<&ArgumentSpec<'p>> {
+        self.arguments.iter().find(|a| a.has_name(name))
+    }
+
+    /// May this template be used inside the template `parent`?
+    pub fn allowed_in(&self, parent: &TemplateSpec) -> bool {
+        if parent.default_name() == self.default_name() {
+            return self.nestable;
+        }
+        parent
+            .children
+            .as_ref()
+            .map(|children| children.iter().any(|c| self.has_name(c)))
+            .unwrap_or(true)
+    }
+}
+
+impl<'p> fmt::Debug for ArgumentSpec<'p> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArgumentSpec")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("priority", &self.priority)
+            .field("predicate", &self.predicate_name)
+            .finish()
+    }
+}
+
+impl<'p> fmt::Debug for TemplateSpec<'p> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TemplateSpec")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("arguments", &self.arguments)
+            .field("parents", &self.parents)
+            .field("children", &self.children)
+            .field("nestable", &self.nestable)
+            .finish()
+    }
+}
+
+/// Find the specification of a template by one of its names.
+pub fn spec_of<'s, 'p>(specs: &'s [TemplateSpec<'p>], name: &str) -> Option<&'s TemplateSpec<'p>> {
+    specs.iter().find(|s| s.has_name(name))
+}
+
+fn boxed<'p, F>(predicate: F) -> Box<Predicate + 'p>
+where
+    F: for<'e> Fn(&'e [Element]) -> PredicateResult<'e> + 'p,
+{
+    Box::new(predicate)
+}
+
+/// Nesting relations of the MFNF templates as
+/// (template, allowed parents, allowed children, nestable).
+const BUILTIN_NESTING: &[(&str, Option<&[&str]>, Option<&[&str]>, bool)] = &[
+    ("Formel", None, Some(&[]), false),
+    (":Mathe für Nicht-Freaks: Vorlage:Hinweis", None, None, false),
+    (":Mathe für Nicht-Freaks: Vorlage:Warnung", None, None, false),
+    (":Mathe für Nicht-Freaks: Vorlage:Gruppenaufgabe", None, None, false),
+    (
+        ":Mathe für Nicht-Freaks: Vorlage:Beweisschritt",
+        Some(&[
+            ":Mathe für Nicht-Freaks: Vorlage:Beweis",
+            ":Mathe für Nicht-Freaks: Vorlage:Satz",
+            ":Mathe für Nicht-Freaks: Vorlage:Aufgabe",
+        ]),
+        None,
+        true,
+    ),
+];
+
+fn to_strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+/// The template specification of the MFNF project compiled into mwlint.
+pub fn builtin<'p>() -> Vec<TemplateSpec<'p>> {
+    let mut specs = builtin_templates::<'p>();
+    for (name, parents, children, nestable) in BUILTIN_NESTING {
+        if let Some(template) = specs.iter_mut().find(|s| s.has_name(name)) {
+            template.parents = parents.map(to_strings);
+            template.children = children.map(to_strings);
+            template.nestable = *nestable;
+        }
+    }
+    specs
+}
+
+fn builtin_templates<'p>() -> Vec<TemplateSpec<'p>> {
+    spec::<'p>()
+        .into_iter()
+        .map(|template| TemplateSpec {
+            name: template.default_name().to_string(),
+            aliases: template.names.iter().skip(1).cloned().collect(),
+            description: template.description.clone(),
+            arguments: template
+                .attributes
+                .into_iter()
+                .map(|attribute| {
+                    let compiled = attribute.predicate;
+                    ArgumentSpec {
+                        name: attribute.default_name().to_string(),
+                        aliases: attribute.names.iter().skip(1).cloned().collect(),
+                        description: attribute.description.clone(),
+                        priority: if attribute.priority == spec_meta::Priority::Required {
+                            Priority::Required
+                        } else {
+                            Priority::Optional
+                        },
+                        predicate_name: attribute.predicate_name.clone(),
+                        predicate: Some(boxed(move |content| {
+                            compiled(content).map_err(|e| PredicateError {
+                                tree: e.tree,
+                                cause: e.cause,
+                            })
+                        })),
+                    }
+                })
+                .collect(),
+            parents: None,
+            children: None,
+            nestable: true,
+        })
+        .collect()
+}
+
+// a predicate used in the built-in specification, by name.
+fn builtin_predicate<'p>(name: &str) -> Option<Box<Predicate + 'p>> {
+    builtin_templates::<'p>()
+        .into_iter()
+        .flat_map(|template| template.arguments)
+        .find(|argument| argument.predicate_name == name)
+        .and_then(|argument| argument.predicate)
+}
+
+/// Read a template specification from a YAML or JSON file.
+///
+/// The predicates of all arguments are resolved from the predicate library.
+pub fn load(path: &Path) -> io::Result<Vec<TemplateSpec<'static>>> {
+    let invalid = |e: &fmt::Display| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    };
+    let file = fs::File::open(path)?;
+    let mut specs: Vec<TemplateSpec> = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_reader(&file).map_err(|e| invalid(&e))?,
+        _ => serde_yaml::from_reader(&file).map_err(|e| invalid(&e))?,
+    };
+    for template in &mut specs {
+        for argument in &mut template.arguments {
+            argument.resolve_predicate().map_err(|e| invalid(&e))?;
+        }
+    }
+    Ok(specs)
+}
+
+// anchor of a template heading in the generated documentation. like the
+// heading slugs of GitHub, punctuation is removed and spaces become `-`.
+fn anchor(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+// description of the predicate of an argument, or its name if it is not
+// part of the predicate library.
+fn predicate_description<'a>(argument: &'a ArgumentSpec) -> &'a str {
+    predicate_info(&argument.predicate_name)
+        .map(|p| p.description)
+        .unwrap_or(&argument.predicate_name)
+}
+
+/// An example call of a template with all its arguments.
+pub fn example(template: &TemplateSpec) -> String {
+    if template.arguments.is_empty() {
+        return format!("{{{{{}}}}}", template.default_name());
+    }
+    let mut result = format!("{{{{{}", template.default_name());
+    for argument in &template.arguments {
+        result.push_str(&format!("\n|{}=...", argument.default_name()));
+    }
+    result.push_str("\n}}");
+    result
+}
+
+// nesting restrictions as pairs of an explanation and the templates it refers to.
+fn nesting<'t>(template: &'t TemplateSpec) -> Vec<(&'static str, &'t [String])> {
+    let mut result = vec![];
+    if let Some(ref parents) = template.parents {
+        result.push(("Only allowed inside:", parents.as_slice()));
+    }
+    match template.children {
+        Some(ref children) if children.is_empty() => {
+            result.push(("No other templates are allowed inside.", children.as_slice()))
+        }
+        Some(ref children) => result.push(("Templates allowed inside:", children.as_slice())),
+        None => (),
+    }
+    if !template.nestable {
+        result.push(("Can not be used inside itself.", &[][..]));
+    }
+    result
+}
+
+/// Render the documentation of a template as markdown.
+pub fn markdown(template: &TemplateSpec, level: usize) -> String {
+    let code = |names: &[String]| {
+        names
+            .iter()
+            .map(|n| format!("`{}`", n))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let heading = "#".repeat(level);
+    let mut result = format!("{} {}\n\n", heading, template.default_name());
+    if !template.description.is_empty() {
+        result.push_str(&format!("{}\n\n", template.description));
+    }
+    if !template.aliases.is_empty() {
+        result.push_str(&format!(
+            "Deprecated names: {}\n\n",
+            code(&template.aliases)
+        ));
+    }
+    for (explanation, names) in nesting(template) {
+        let links: Vec<String> = names
+            .iter()
+            .map(|n| format!("[`{}`](#{})", n, anchor(n)))
+            .collect();
+        if links.is_empty() {
+            result.push_str(&format!("{}\n\n", explanation));
+        } else {
+            result.push_str(&format!("{} {}\n\n", explanation, links.join(", ")));
+        }
+    }
+    if !template.arguments.is_empty() {
+        result.push_str("| Argument | Deprecated names | Required | Content | Description |\n");
+        result.push_str("|----------|------------------|----------|---------|-------------|\n");
+        for argument in &template.arguments {
+            result.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} |\n",
+                argument.default_name(),
+                code(&argument.aliases),
+                if argument.priority == Priority::Required {
+                    "yes"
+                } else {
+                    "no"
+                },
+                predicate_description(argument),
+                argument.description.replace('\n', " ")
+            ));
+        }
+        result.push('\n');
+    }
+    result.push_str(&format!("Example:\n\n```\n{}\n```\n", example(template)));
+    result
+}
+
+/// Render the documentation of a template as html.
+pub fn html(template: &TemplateSpec, level: usize) -> String {
+    let code = |names: &[String]| {
+        names
+            .iter()
+            .map(|n| format!("<code>{}</code>", escape_html(n)))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let level = level.max(1).min(6);
+    let mut result = format!(
+        "<h{level} id=\"{}\">{}</h{level}>\n",
+        escape_html(&anchor(template.default_name())),
+        escape_html(template.default_name()),
+        level = level
+    );
+    if !template.description.is_empty() {
+        result.push_str(&format!("<p>{}</p>\n", escape_html(&template.description)));
+    }
+    if !template.aliases.is_empty() {
+        result.push_str(&format!(
+            "<p>Deprecated names: {}</p>\n",
+            code(&template.aliases)
+        ));
+    }
+    for (explanation, names) in nesting(template) {
+        let links: Vec<String> = names
+            .iter()
+            .map(|n| {
+                format!(
+                    "<a href=\"#{}\"><code>{}</code></a>",
+                    escape_html(&anchor(n)),
+                    escape_html(n)
+                )
+            })
+            .collect();
+        if links.is_empty() {
+            result.push_str(&format!("<p>{}</p>\n", explanation));
+        } else {
+            result.push_str(&format!("<p>{} {}</p>\n", explanation, links.join(", ")));
+        }
+    }
+    if !template.arguments.is_empty() {
+        result.push_str(
+            "<table>\n<tr><th>Argument</th><th>Deprecated names</th><th>Required</th>\
+             <th>Content</th><th>Description</th></tr>\n",
+        );
+        for argument in &template.arguments {
+            result.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(argument.default_name()),
+                code(&argument.aliases),
+                if argument.priority == Priority::Required {
+                    "yes"
+                } else {
+                    "no"
+                },
+                escape_html(predicate_description(argument)),
+                escape_html(&argument.description)
+            ));
+        }
+        result.push_str("</table>\n");
+    }
+    result.push_str(&format!(
+        "<p>Example:</p>\n<pre>{}</pre>\n",
+        escape_html(&example(template))
+    ));
+    result
+}
+
+/// The documentation of a template as JSON, i.e. the specification with
+/// predicate descriptions and an example call.
+pub fn json(template: &TemplateSpec) -> serde_json::Value {
+    let mut value = serde_json::to_value(template).unwrap_or_default();
+    if let Some(arguments) = value.get_mut("arguments").and_then(|a| a.as_array_mut()) {
+        for (argument, spec) in arguments.iter_mut().zip(&template.arguments) {
+            argument["predicate_description"] = predicate_description(spec).into();
+        }
+    }
+    value["example"] = example(template).into();
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declarative_spec() {
+        let source = "
+- name: Formel
+  aliases: [Formula]
+  arguments:
+    - name: '1'
+      priority: required
+      predicate: is_math_tag
+";
+        let mut specs: Vec<TemplateSpec> = serde_yaml::from_str(source).unwrap();
+        for argument in &mut specs[0].arguments {
+            argument.resolve_predicate().unwrap();
+        }
+        let formula = spec_of(&specs, "formula").unwrap();
+        assert_eq!(formula.default_name(), "Formel");
+        assert!(formula.nestable);
+        assert!(formula.allowed_in(formula));
+
+        let argument = formula.argument("1").unwrap();
+        assert_eq!(argument.priority, Priority::Required);
+        let good = parse("<math>x^2</math>").unwrap();
+        let bad = parse("just text").unwrap();
+        match (good, bad) {
+            (Element::Document(ref good), Element::Document(ref bad)) => {
+                let good_content = match good.content[0] {
+                    Element::Paragraph(ref p) => &p.content,
+                    _ => &good.content,
+                };
+                let bad_content = match bad.content[0] {
+                    Element::Paragraph(ref p) => &p.content,
+                    _ => &bad.content,
+                };
+                assert!(argument.check(good_content).is_ok());
+                assert!(argument.check(bad_content).is_err());
+            }
+            _ => panic!("the parser did not return documents!"),
+        }
+    }
+
+    #[test]
+    fn builtin_round_trip() {
+        let dumped = serde_yaml::to_string(&builtin()).unwrap();
+        let mut specs: Vec<TemplateSpec> = serde_yaml::from_str(&dumped).unwrap();
+        for template in &mut specs {
+            for argument in &mut template.arguments {
+                argument.resolve_predicate().unwrap();
+            }
+        }
+        assert_eq!(serde_yaml::to_string(&specs).unwrap(), dumped);
+    }
+
+    #[test]
+    fn unknown_predicate() {
+        let source = "
+- name: Formel
+  arguments:
+    - name: '1'
+      predicate: is_a_formula
+";
+        let mut specs: Vec<TemplateSpec> = serde_yaml::from_str(source).unwrap();
+        assert!(specs[0].arguments[0].resolve_predicate().is_err());
+    }
+
+    #[test]
+    fn documentation() {
+        let source = "
+- name: Beweisschritt
+  aliases: [Schritt]
+  parents: [Beweis]
+  arguments:
+    - name: ziel
+      aliases: [Ziel]
+      priority: required
+      predicate: is_inline
+      description: Goal of the <step>.
+    - name: begründung
+      predicate: is_reason
+";
+        let specs: Vec<TemplateSpec> = serde_yaml::from_str(source).unwrap();
+        let markdown = markdown(&specs[0], 2);
+        assert!(markdown.starts_with("## Beweisschritt\n"));
+        assert!(markdown.contains("[`Beweis`](#beweis)"));
+        assert!(markdown.contains("| `ziel` | `Ziel` | yes | Only inline markup"));
+        assert!(markdown.contains("| `begründung` |  | no | is_reason |"));
+        assert!(markdown.contains("{{Beweisschritt\n|ziel=...\n|begründung=...\n}}"));
+        assert_eq!(
+            anchor(":Mathe für Nicht-Freaks: Vorlage:Beweis"),
+            "mathe-für-nicht-freaks-vorlagebeweis"
+        );
+
+        let html = html(&specs[0], 2);
+        assert!(html.contains("<h2 id=\"beweisschritt\">"));
+        assert!(html.contains("Goal of the &lt;step&gt;."));
+
+        let json = json(&specs[0]);
+        assert_eq!(json["name"], "Beweisschritt");
+        assert!(json["arguments"][0]["predicate_description"].is_string());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::rule::ElementType;
+use crate::settings::Settings;
+use crate::template_spec::spec_of;
+use mediawiki_parser::transformations::*;
+use mediawiki_parser::*;
+use mwparser_utils::extract_plain_text;
+use std::mem;
+
+/// A step of the normalization applied to documents before checking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Convert list templates to lists.
+    TemplateList,
+    /// Remove whitespace around template names.
+    TrimTemplateNames,
+    /// Merge adjacent text elements.
+    MergeText,
+    /// Replace deprecated template and argument names by their default
+    /// names. Rules can not find deprecated names afterwards.
+    ResolveAliases,
+    /// Remove comments.
+    StripComments,
+}
+
+/// Lists of child elements which may contain text.
+fn child_lists(element: &mut Element) -> Vec<&mut Vec<Element>> {
+    match *element {
+        Element::Document(ref mut e) => vec![&mut e.content],
+        Element::Heading(ref mut e) => vec![&mut e.caption, &mut e.content],
+        Element::Formatted(ref mut e) => vec![&mut e.content],
+        Element::Paragraph(ref mut e) => vec![&mut e.content],
+        Element::Template(ref mut e) => vec![&mut e.name, &mut e.content],
+        Element::TemplateArgument(ref mut e) => vec![&mut e.value],
+        Element::InternalReference(ref mut e) => vec![&mut e.target, &mut e.caption],
+        Element::ExternalReference(ref mut e) => vec![&mut e.caption],
+        Element::ListItem(ref mut e) => vec![&mut e.content],
+        Element::TableCell(ref mut e) => vec![&mut e.content],
+        Element::HtmlTag(ref mut e) => vec![&mut e.content],
+        _ => vec![],
+    }
+}
+
+fn trim_template_names(mut root: Element, settings: ()) -> TResult {
+    if let Element::Template(ref mut template) = root {
+        if let Some(Element::Text(ref mut text)) = template.name.first_mut() {
+            text.text = text.text.trim_start().to_string();
+        }
+        if let Some(Element::Text(ref mut text)) = template.name.last_mut() {
+            text.text = text.text.trim_end().to_string();
+        }
+    }
+    recurse_inplace(&trim_template_names, root, settings)
+}
+
+fn merge_text(mut root: Element, settings: ()) -> TResult {
+    for list in child_lists(&mut root) {
+        let mut merged: Vec<Element> = Vec::with_capacity(list.len());
+        for element in mem::replace(list, vec![]) {
+            if let (Some(Element::Text(ref mut last)), Element::Text(ref text)) =
+                (merged.last_mut(), &element)
+            {
+                last.text.push_str(&text.text);
+                last.position.end = text.position.end.clone();
+                continue;
+            }
+            merged.push(element);
+        }
+        *list = merged;
+    }
+    recurse_inplace(&merge_text, root, settings)
+}
+
+fn resolve_aliases(mut root: Element, settings: &Settings) -> TResult {
+    if let Element::Template(ref mut template) = root {
+        let name = extract_plain_text(&template.name);
+        if let Some(spec) = spec_of(&settings.template_spec, name.trim()) {
+            if spec.default_name() != name.trim() {
+                let mut position = template.name[0].get_position().clone();
+                position.end = template.name[template.name.len() - 1]
+                    .get_position()
+                    .end
+                    .clone();
+                template.name = vec![Element::Text(Text {
+                    position,
+                    text: spec.default_name().to_string(),
+                })];
+            }
+            for argument in &mut template.content {
+                if let Element::TemplateArgument(ref mut arg) = *argument {
+                    if let Some(arg_spec) = spec.argument(arg.name.trim()) {
+                        arg.name = arg_spec.default_name().to_string();
+                    }
+                }
+            }
+        }
+    }
+    recurse_inplace(&resolve_aliases, root, settings)
+}
+
+fn strip_comments(mut root: Element, settings: ()) -> TResult {
+    for list in child_lists(&mut root) {
+        list.retain(|e| ElementType::from(e) != ElementType::Comment);
+    }
+    recurse_inplace(&strip_comments, root, settings)
+}
+
+/// Apply a normalization step to a tree.
+pub fn apply(step: Normalization, root: Element, settings: &Settings) -> TResult {
+    match step {
+        Normalization::TemplateList => mwparser_utils::transformations::convert_template_list(root),
+        Normalization::TrimTemplateNames => trim_template_names(root, ()),
+        Normalization::MergeText => merge_text(root, ()),
+        Normalization::ResolveAliases => resolve_aliases(root, settings),
+        Normalization::StripComments => strip_comments(root, ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::collect_elements;
+    use std::slice;
+
+    fn normalize(steps: &[Normalization], source: &str) -> Element {
+        let mut settings = Settings::default();
+        settings.normalization = steps.to_vec();
+        crate::normalize(parse(source).unwrap(), &settings).unwrap()
+    }
+
+    fn template_names(root: &Element) -> Vec<String> {
+        collect_elements(root, |e| ElementType::from(e) == ElementType::Template)
+            .into_iter()
+            .filter_map(|e| match *e {
+                Element::Template(ref t) => Some(extract_plain_text(&t.name)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn template_name_steps() {
+        let source = "{{ formel |1=<math>x</math>}}";
+        let root = normalize(&[Normalization::TrimTemplateNames], source);
+        assert_eq!(template_names(&root)[0], "formel");
+        let root = normalize(&[Normalization::ResolveAliases], source);
+        assert_eq!(template_names(&root)[0], "Formel");
+    }
+
+    #[test]
+    fn text_and_comments() {
+        use self::Normalization::*;
+        let source = "a<!-- comment -->b";
+        let root = normalize(&[StripComments, MergeText], source);
+        let texts = collect_elements(&root, |e| {
+            let element_type = ElementType::from(e);
+            element_type == ElementType::Text || element_type == ElementType::Comment
+        });
+        assert_eq!(texts.len(), 1);
+        assert_eq!(extract_plain_text(slice::from_ref(texts[0])), "ab");
+    }
+}
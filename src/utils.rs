@@ -0,0 +1,234 @@
+use crate::lint::Replacement;
+use mediawiki_parser::*;
+use std::cmp;
+use std::io;
+
+/// Value of an attribute of a html tag, without quotes.
+pub fn tag_attribute<'a>(tag: &'a HtmlTag, key: &str) -> Option<&'a str> {
+    tag.attributes
+        .iter()
+        .find(|attr| attr.key.trim().to_lowercase() == key)
+        .map(|attr| {
+            attr.value
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .trim()
+        })
+}
+
+/// Collects all elements matching a predicate in source order.
+struct Collector<'e, F> {
+    path: Vec<&'e Element>,
+    found: Vec<&'e Element>,
+    predicate: F,
+}
+
+impl<'e, F: Fn(&Element) -> bool> Traversion<'e, ()> for Collector<'e, F> {
+    path_impl!();
+
+    fn work(&mut self, root: &'e Element, _: (), _: &mut io::Write) -> io::Result<bool> {
+        if (self.predicate)(root) {
+            self.found.push(root);
+        }
+        Ok(true)
+    }
+}
+
+/// Get all elements of a tree matching a predicate in source order.
+pub fn collect_elements<'e, F>(root: &'e Element, predicate: F) -> Vec<&'e Element>
+where
+    F: Fn(&Element) -> bool,
+{
+    let mut collector = Collector {
+        path: vec![],
+        found: vec![],
+        predicate,
+    };
+    // the collector itself never fails.
+    collector
+        .run(root, (), &mut vec![])
+        .expect("collecting elements failed!");
+    collector.found
+}
+
+/// Get all html tags with a given name in source order.
+pub fn collect_tags<'e>(root: &'e Element, name: &str) -> Vec<&'e HtmlTag> {
+    collect_elements(root, |e| {
+        if let Element::HtmlTag(ref tag) = *e {
+            tag.name.trim().to_lowercase() == name
+        } else {
+            false
+        }
+    })
+    .into_iter()
+    .filter_map(|e| {
+        if let Element::HtmlTag(ref tag) = *e {
+            Some(tag)
+        } else {
+            None
+        }
+    })
+    .collect()
+}
+
+/// Normalize a page title the way MediaWiki does for comparison:
+/// underscores are spaces and the first letter is case-insensitive.
+pub fn normalize_title(title: &str) -> String {
+    let title = title.replace('_', " ");
+    let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Edit distance between two strings, counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + cmp::min(diagonal, cmp::min(above, row[j]))
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Fold a name for fuzzy comparison: case, umlauts, whitespace and
+/// separators are ignored.
+pub fn fold_name(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        match c {
+            'ä' => result.push_str("ae"),
+            'ö' => result.push_str("oe"),
+            'ü' => result.push_str("ue"),
+            'ß' => result.push_str("ss"),
+            ' ' | '_' | '-' | '\t' => (),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Find the candidates closest to a name, best matches first.
+///
+/// Names are compared by their edit distance after folding (see `fold_name`),
+/// which may be a quarter of the length of the name (at least one), so short
+/// names do not match unrelated ones.
+pub fn closest_matches<'c, I>(name: &str, candidates: I) -> Vec<&'c str>
+where
+    I: IntoIterator<Item = &'c str>,
+{
+    let folded = fold_name(name);
+    let max_distance = cmp::max(1, folded.chars().count() / 4);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|c| (levenshtein(&folded, &fold_name(c)), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.dedup_by(|a, b| a.1 == b.1);
+    matches.into_iter().take(3).map(|(_, c)| c).collect()
+}
+
+/// Reproduce the markup of simple elements. Returns `None` if some element
+/// can not be reproduced faithfully.
+pub fn to_wikitext(elements: &[Element]) -> Option<String> {
+    let mut result = String::new();
+    for element in elements {
+        match *element {
+            Element::Text(ref text) => result.push_str(&text.text),
+            Element::Comment(ref comment) => {
+                result.push_str(&format!("<!--{}-->", comment.text));
+            }
+            Element::Formatted(ref formatted) => {
+                let (open, close) = match formatted.markup {
+                    MarkupType::Math => ("<math>", "</math>"),
+                    MarkupType::Bold => ("'''", "'''"),
+                    MarkupType::Italic => ("''", "''"),
+                    _ => return None,
+                };
+                result.push_str(open);
+                result.push_str(&to_wikitext(&formatted.content)?);
+                result.push_str(close);
+            }
+            Element::Template(ref template) => {
+                result.push_str("{{");
+                result.push_str(&to_wikitext(&template.name)?);
+                for argument in &template.content {
+                    if let Element::TemplateArgument(ref arg) = *argument {
+                        result.push('|');
+                        if !arg.name.chars().all(|c| c.is_ascii_digit()) {
+                            result.push_str(&arg.name);
+                            result.push('=');
+                        }
+                        result.push_str(&to_wikitext(&arg.value)?);
+                    }
+                }
+                result.push_str("}}");
+            }
+            Element::InternalReference(ref link) => {
+                result.push_str("[[");
+                result.push_str(&to_wikitext(&link.target)?);
+                for option in &link.options {
+                    result.push('|');
+                    result.push_str(&to_wikitext(option)?);
+                }
+                if !link.caption.is_empty() {
+                    result.push('|');
+                    result.push_str(&to_wikitext(&link.caption)?);
+                }
+                result.push_str("]]");
+            }
+            Element::ExternalReference(ref link) => {
+                result.push('[');
+                result.push_str(&link.target);
+                if !link.caption.is_empty() {
+                    result.push(' ');
+                    result.push_str(&to_wikitext(&link.caption)?);
+                }
+                result.push(']');
+            }
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Escape text for html.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Apply replacements to the source text they were found in. Replacements
+/// overlapping a previously applied one are skipped.
+pub fn apply_replacements(source: &str, replacements: &[Replacement]) -> String {
+    let mut sorted: Vec<&Replacement> = replacements.iter().collect();
+    sorted.sort_by_key(|r| (r.position.start.offset, r.position.end.offset));
+    let mut result = String::new();
+    let mut end = 0;
+    for replacement in sorted {
+        let position = &replacement.position;
+        if position.start.offset < end || position.end.offset > source.len() {
+            continue;
+        }
+        result.push_str(&source[end..position.start.offset]);
+        result.push_str(&replacement.text);
+        end = position.end.offset;
+    }
+    result.push_str(&source[end..]);
+    result
+}
@@ -40,31 +40,17 @@ fn render_example(example: &mut mwlint::Example) {
 /// Naive linter function. Outputs result as serialized JSON.
 #[wasm_bindgen]
 pub fn lint(input: &str) -> String {
-    let settings = mwlint::Settings::default();
-
-    let mut tree = mediawiki_parser::parse(&input)
-        .map_err(|e| LintResult::Error(e));
-
-    tree = tree.and_then(|t|
-            mwlint::normalize(t, &settings)
-                .map_err(|e| LintResult::Error(MWError::TransformationError(e))));
-
-    let result = tree.map(|tree| {
-        let mut rules = mwlint::get_rules();
-        let mut lints = vec![];
-
-        for mut rule in &mut rules {
-            rule.run(&tree, &settings, &mut vec![])
-                .expect("error while checking rule!");
-            lints.append(&mut rule.lints().iter().map(|l| l.clone()).collect())
-        }
-
-        for mut lint in &mut lints {
-            render_lint(&mut lint);
-        }
-
-        LintResult::Lints(lints)
-    });
+    let linter = mwlint::Linter::new(mwlint::Settings::default());
+
+    let result = linter.lint_str(input)
+        .map(|report| {
+            let mut lints = report.lints;
+            for mut lint in &mut lints {
+                render_lint(&mut lint);
+            }
+            LintResult::Lints(lints)
+        })
+        .map_err(LintResult::Error);
 
     serde_json::to_string(&result)
         .expect("could not serialize lints")